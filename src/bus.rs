@@ -0,0 +1,49 @@
+use log::debug;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedValue;
+
+/// Lazily connected system bus for reading properties of optional desktop services.
+/// Every failure is logged and reported as `None`, so callers can fall back quietly.
+#[derive(Default)]
+pub struct SystemBus {
+    conn: Option<Connection>,
+}
+
+impl SystemBus {
+    pub fn property<T>(
+        &mut self,
+        service: &'static str,
+        path: &'static str,
+        interface: &'static str,
+        name: &str,
+    ) -> Option<T>
+    where
+        T: TryFrom<OwnedValue>,
+        T::Error: Into<zbus::Error>,
+    {
+        let conn = self.connection()?;
+        let value = Proxy::new(conn, service, path, interface)
+            .and_then(|proxy| proxy.get_property::<T>(name));
+
+        match value {
+            Ok(value) => Some(value),
+            Err(err) => {
+                debug!("{service} {name} unavailable: {err}");
+                None
+            }
+        }
+    }
+
+    fn connection(&mut self) -> Option<&Connection> {
+        if self.conn.is_none() {
+            match Connection::system() {
+                Ok(conn) => self.conn = Some(conn),
+                Err(err) => {
+                    debug!("system bus unavailable: {err}");
+                    return None;
+                }
+            }
+        }
+        self.conn.as_ref()
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands::DetectedAurHelper;
+use crate::state::{AppState, UpdateSnapshot};
+
+/// The last successful check, persisted so the tray has something to show on startup.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CachedCheck {
+    pub helper: Option<DetectedAurHelper>,
+    pub state: AppState,
+    pub snapshot: UpdateSnapshot,
+}
+
+pub fn default_cache_path() -> PathBuf {
+    let base = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+    base.join("pactrack").join("last_check.toml")
+}
+
+pub fn load_cached_check(path: &Path) -> io::Result<Option<CachedCheck>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let raw = fs::read_to_string(path)?;
+    toml::from_str(&raw)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn save_cached_check(path: &Path, cached: &CachedCheck) -> io::Result<()> {
+    let raw =
+        toml::to_string(cached).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Write to a sibling file first so a crash never leaves a truncated cache behind.
+    let tmp = path.with_extension("toml.tmp");
+    fs::write(&tmp, raw)?;
+    fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+
+    use super::*;
+    use crate::state::{
+        InstallReason, PackageUpdate, RebuildHint, SourceState, Status, UpdateSource,
+    };
+
+    #[test]
+    fn cached_check_round_trips() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("nested").join("last_check.toml");
+        let now = Local::now();
+
+        let mut icu = PackageUpdate::new("icu", "74.1-1", "74.2-1", UpdateSource::Official);
+        icu.reason = InstallReason::Dependency;
+        icu.first_seen = Some(now);
+        let snapshot = UpdateSnapshot {
+            official: vec![icu],
+            aur: vec![PackageUpdate::new(
+                "foo-git",
+                "r1-1",
+                "r2-1",
+                UpdateSource::Aur,
+            )],
+            rebuilds: vec![RebuildHint {
+                package: "icu".to_string(),
+                current: "74.1-1".to_string(),
+                latest: "74.2-1".to_string(),
+                dependents: 3,
+                aur_dependents: Vec::new(),
+                minor_bump: false,
+            }],
+            snoozed: Vec::new(),
+        };
+        let cached = CachedCheck {
+            helper: Some(DetectedAurHelper::Paru),
+            state: AppState::from_sources(
+                &snapshot,
+                SourceState {
+                    last_checked: Some(now),
+                    ..SourceState::default()
+                },
+                Some(SourceState::default()),
+            ),
+            snapshot: snapshot.clone(),
+        };
+
+        save_cached_check(&path, &cached).expect("save cache");
+        let loaded = load_cached_check(&path)
+            .expect("load cache")
+            .expect("cache present");
+
+        assert_eq!(loaded.snapshot, snapshot);
+        assert_eq!(loaded.helper, Some(DetectedAurHelper::Paru));
+        assert_eq!(loaded.state.status, Status::UpdatesAvailable);
+        assert_eq!(loaded.state.last_checked, Some(now));
+        assert!(!loaded.state.stale);
+        assert_eq!(loaded.state.official.last_checked, Some(now));
+        assert!(loaded.state.aur.is_some());
+    }
+
+    #[test]
+    fn cache_from_an_older_version_still_loads() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("last_check.toml");
+        fs::write(
+            &path,
+            r#"
+[state]
+status = "UpdatesAvailable"
+official_count = 1
+aur_count = 0
+total_count = 1
+
+[state.official]
+last_checked = "2024-03-05T10:00:00+01:00"
+
+[snapshot]
+aur = []
+
+[[snapshot.official]]
+name = "icu"
+current = "74.1-1"
+latest = "74.2-1"
+source = "Official"
+"#,
+        )
+        .expect("write cache");
+
+        let loaded = load_cached_check(&path)
+            .expect("load cache")
+            .expect("cache present");
+        assert_eq!(loaded.state.consecutive_failures, 0);
+        assert!(loaded.state.official.last_checked.is_some());
+        assert!(loaded.state.official.last_success.is_none());
+        assert_eq!(loaded.snapshot.official[0].reason, InstallReason::Explicit);
+        assert!(loaded.snapshot.rebuilds.is_empty());
+    }
+
+    #[test]
+    fn missing_cache_is_not_an_error() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let loaded = load_cached_check(&temp.path().join("missing.toml")).expect("load");
+        assert!(loaded.is_none());
+    }
+}
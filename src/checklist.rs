@@ -0,0 +1,194 @@
+use std::rc::Rc;
+
+use gtk::prelude::*;
+
+use crate::commands::DetectedAurHelper;
+use crate::state::{UpdateSnapshot, UpdateSource};
+use crate::upgrade::{Selection, UpgradePlan, plan_upgrade, selection_key};
+
+const COL_SELECTED: u32 = 0;
+const COL_NAME: u32 = 1;
+const COL_SOURCE: u32 = 2;
+const COL_VERSIONS: u32 = 3;
+/// Hidden: whether the row is an AUR update, to tell apart names pending from both sources.
+const COL_AUR: u32 = 4;
+
+const RESPONSE_SELECT_ALL: u16 = 1;
+const RESPONSE_SELECT_NONE: u16 = 2;
+
+/// Opens a checklist of the pending updates in `snapshot` with `preselected` ticked. The
+/// planned command and its warnings follow the selection; "Upgrade" passes the command to
+/// `on_confirm` and closes the dialog. `upgrade_cmd` is the configured upgrade command.
+pub fn open_upgrade_checklist(
+    snapshot: &UpdateSnapshot,
+    preselected: &Selection,
+    helper: Option<DetectedAurHelper>,
+    upgrade_cmd: String,
+    on_confirm: impl Fn(String) + 'static,
+) {
+    let dialog = gtk::Dialog::new();
+    dialog.set_title("Choose updates to install");
+    dialog.set_default_size(560, 520);
+    dialog.add_button("Select all", gtk::ResponseType::Other(RESPONSE_SELECT_ALL));
+    dialog.add_button(
+        "Select none",
+        gtk::ResponseType::Other(RESPONSE_SELECT_NONE),
+    );
+    dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+    dialog.add_button("Upgrade", gtk::ResponseType::Accept);
+
+    let store = gtk::ListStore::new(&[
+        glib::Type::BOOL,
+        glib::Type::STRING,
+        glib::Type::STRING,
+        glib::Type::STRING,
+        glib::Type::BOOL,
+    ]);
+    for update in snapshot.all() {
+        store.insert_with_values(
+            None,
+            &[
+                (COL_SELECTED, &preselected.contains(&selection_key(update))),
+                (COL_NAME, &update.name),
+                (COL_SOURCE, &update.source.label()),
+                (
+                    COL_VERSIONS,
+                    &format!("{} → {}", update.current, update.latest),
+                ),
+                (COL_AUR, &(update.source == UpdateSource::Aur)),
+            ],
+        );
+    }
+
+    let view = gtk::TreeView::with_model(&store);
+    let toggle = gtk::CellRendererToggle::new();
+    let toggle_column = gtk::TreeViewColumn::new();
+    CellLayoutExt::pack_start(&toggle_column, &toggle, false);
+    CellLayoutExt::add_attribute(&toggle_column, &toggle, "active", COL_SELECTED as i32);
+    view.append_column(&toggle_column);
+    for (title, col) in [
+        ("Package", COL_NAME),
+        ("Source", COL_SOURCE),
+        ("Version", COL_VERSIONS),
+    ] {
+        let cell = gtk::CellRendererText::new();
+        let column = gtk::TreeViewColumn::new();
+        column.set_title(title);
+        CellLayoutExt::pack_start(&column, &cell, true);
+        CellLayoutExt::add_attribute(&column, &cell, "text", col as i32);
+        view.append_column(&column);
+    }
+
+    let scrolled = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    scrolled.set_vexpand(true);
+    scrolled.add(&view);
+
+    let warnings = gtk::Label::new(None);
+    warnings.set_xalign(0.0);
+    warnings.set_line_wrap(true);
+    let command = gtk::Label::new(None);
+    command.set_xalign(0.0);
+    command.set_line_wrap(true);
+    command.set_selectable(true);
+
+    let content = dialog.content_area();
+    content.set_spacing(6);
+    content.set_border_width(8);
+    content.pack_start(&scrolled, true, true, 0);
+    content.pack_start(&warnings, false, false, 0);
+    content.pack_start(&command, false, false, 0);
+
+    let snapshot = Rc::new(snapshot.clone());
+    let upgrade_cmd = Rc::new(upgrade_cmd);
+    let refresh = {
+        let (store, dialog) = (store.clone(), dialog.clone());
+        let (snapshot, upgrade_cmd) = (Rc::clone(&snapshot), Rc::clone(&upgrade_cmd));
+        Rc::new(move || {
+            let plan = plan_upgrade(&snapshot, &selected(&store), helper, &upgrade_cmd);
+            show_plan(&plan, &warnings, &command);
+            dialog.set_response_sensitive(gtk::ResponseType::Accept, plan.command.is_some());
+        })
+    };
+
+    {
+        let (store, refresh) = (store.clone(), Rc::clone(&refresh));
+        toggle.connect_toggled(move |_, path| {
+            if let Some(iter) = store.iter(&path) {
+                let selected = store
+                    .value(&iter, COL_SELECTED as i32)
+                    .get::<bool>()
+                    .unwrap_or(false);
+                store.set_value(&iter, COL_SELECTED, &(!selected).to_value());
+                refresh();
+            }
+        });
+    }
+
+    let on_response_refresh = Rc::clone(&refresh);
+    dialog.connect_response(move |dialog, response| match response {
+        gtk::ResponseType::Other(RESPONSE_SELECT_ALL) => {
+            set_all(&store, true, &on_response_refresh)
+        }
+        gtk::ResponseType::Other(RESPONSE_SELECT_NONE) => {
+            set_all(&store, false, &on_response_refresh)
+        }
+        gtk::ResponseType::Accept => {
+            if let Some(command) =
+                plan_upgrade(&snapshot, &selected(&store), helper, &upgrade_cmd).command
+            {
+                on_confirm(command);
+            }
+            dialog.close();
+        }
+        _ => dialog.close(),
+    });
+
+    dialog.show_all();
+    // After `show_all`, which would also reveal an empty warnings label.
+    refresh();
+    dialog.present();
+}
+
+fn show_plan(plan: &UpgradePlan, warnings: &gtk::Label, command: &gtk::Label) {
+    let text: Vec<String> = plan
+        .warnings
+        .iter()
+        .map(|warning| format!("⚠ {warning}"))
+        .collect();
+    warnings.set_text(&text.join("\n"));
+    warnings.set_visible(!text.is_empty());
+
+    match &plan.command {
+        Some(cmd) => command.set_text(&format!("Will run: {cmd}")),
+        None => command.set_text("Nothing to upgrade."),
+    }
+}
+
+fn selected(store: &gtk::ListStore) -> Selection {
+    let mut selection = Selection::new();
+    store.foreach(|model, _, iter| {
+        let flag = |col: u32| model.value(iter, col as i32).get::<bool>().unwrap_or(false);
+        if flag(COL_SELECTED)
+            && let Ok(name) = model.value(iter, COL_NAME as i32).get::<String>()
+        {
+            let source = if flag(COL_AUR) {
+                UpdateSource::Aur
+            } else {
+                UpdateSource::Official
+            };
+            selection.insert((source, name));
+        }
+        false
+    });
+    selection
+}
+
+fn set_all(store: &gtk::ListStore, selected: bool, refresh: &Rc<impl Fn()>) {
+    store.foreach(|model, path, _| {
+        if let Some(iter) = model.iter(path) {
+            store.set_value(&iter, COL_SELECTED, &selected.to_value());
+        }
+        false
+    });
+    refresh();
+}
@@ -1,19 +1,29 @@
+use std::collections::HashSet;
 use std::env;
 use std::ffi::OsStr;
 use std::fmt;
 use std::fs;
+use std::io::Read;
 use std::os::unix::fs::{PermissionsExt, symlink};
+use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, ExitStatus};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use log::warn;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::config::{AurHelperMode, EffectiveConfig};
-use crate::parser::parse_update_lines;
-use crate::state::{UpdateSnapshot, UpdateSource};
+use crate::localdb::LocalDb;
+use crate::parser::{parse_sync_info, parse_update_lines};
+use crate::rebuilds::find_rebuild_hints;
+use crate::state::{PackageUpdate, RebuildHint, UpdateSnapshot, UpdateSource};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum DetectedAurHelper {
     Paru,
     Yay,
@@ -43,6 +53,8 @@ struct ResolvedCommand {
 pub struct CheckOutcome {
     pub snapshot: UpdateSnapshot,
     pub helper: Option<DetectedAurHelper>,
+    /// Sources whose check failed; their part of `snapshot` is empty.
+    pub failures: Vec<(UpdateSource, CommandError)>,
 }
 
 #[derive(Debug, Error)]
@@ -65,28 +77,220 @@ pub enum CommandError {
         context: String,
         source: std::io::Error,
     },
+    #[error("command `{command}` timed out after {seconds}s")]
+    Timeout { command: String, seconds: u64 },
+    #[error("command `{command}` was cancelled")]
+    Cancelled { command: String },
 }
 
-pub fn perform_check(config: &EffectiveConfig) -> Result<CheckOutcome, CommandError> {
-    let official = run_official_check(config)?;
-    let helper = detect_aur_helper(config.aur_helper, config.enable_aur);
+impl CommandError {
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled { .. })
+    }
+}
 
-    let aur = if config.enable_aur {
-        if let Some(helper) = helper {
-            run_aur_check(helper)?
-        } else {
-            Vec::new()
+/// Upper bound for a single external command when the config does not set one.
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared flag that aborts in-flight commands when set.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Timeout and cancellation applied to every command a check runs.
+#[derive(Clone, Debug)]
+pub struct RunLimits {
+    pub timeout: Duration,
+    pub cancel: CancelToken,
+}
+
+impl RunLimits {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+impl Default for RunLimits {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMMAND_TIMEOUT)
+    }
+}
+
+/// Result of checking one update source.
+#[derive(Debug)]
+pub struct SourceCheck {
+    pub updates: Vec<PackageUpdate>,
+    /// Only filled by official checks.
+    pub rebuilds: Vec<RebuildHint>,
+    /// Only filled by AUR checks.
+    pub helper: Option<DetectedAurHelper>,
+}
+
+/// Checks official and AUR updates concurrently and merges them into one snapshot. A
+/// failing source is reported in `failures` next to the other source's results; when every
+/// enabled source fails, their errors are returned.
+pub fn perform_check(
+    config: &EffectiveConfig,
+) -> Result<CheckOutcome, Vec<(UpdateSource, CommandError)>> {
+    let limits = RunLimits::new(config.command_timeout);
+    let (official, aur) = thread::scope(|scope| {
+        let aur = scope.spawn(|| check_source(config, UpdateSource::Aur, &limits));
+        let official = check_source(config, UpdateSource::Official, &limits);
+        (official, aur.join().expect("AUR check thread panicked"))
+    });
+
+    let mut outcome = CheckOutcome {
+        snapshot: UpdateSnapshot::default(),
+        helper: None,
+        failures: Vec::new(),
+    };
+    match official {
+        Ok(official) => {
+            outcome.snapshot.official = official.updates;
+            outcome.snapshot.rebuilds = official.rebuilds;
+        }
+        Err(err) => outcome.failures.push((UpdateSource::Official, err)),
+    }
+    match aur {
+        Ok(aur) => {
+            outcome.snapshot.aur = aur.updates;
+            outcome.helper = aur.helper;
+        }
+        Err(err) => outcome.failures.push((UpdateSource::Aur, err)),
+    }
+    let enabled = if config.enable_aur { 2 } else { 1 };
+    if outcome.failures.len() == enabled {
+        return Err(outcome.failures);
+    }
+    Ok(outcome)
+}
+
+pub fn check_source(
+    config: &EffectiveConfig,
+    source: UpdateSource,
+    limits: &RunLimits,
+) -> Result<SourceCheck, CommandError> {
+    let real_db_path = resolve_pacman_db_path();
+    let local_db = LocalDb::new(&real_db_path);
+
+    match source {
+        UpdateSource::Official => {
+            let mut updates = run_official_check(config, &real_db_path, limits)?;
+            local_db.annotate_reasons(&mut updates);
+            let rebuilds = collect_rebuild_hints(config, &local_db, &updates, limits);
+            Ok(SourceCheck {
+                updates,
+                rebuilds,
+                helper: None,
+            })
         }
+        UpdateSource::Aur => {
+            let helper = detect_aur_helper(config.aur_helper, config.enable_aur);
+            let mut updates = match helper {
+                Some(helper) if config.enable_aur => run_aur_check(helper, limits)?,
+                _ => Vec::new(),
+            };
+            local_db.annotate_reasons(&mut updates);
+            Ok(SourceCheck {
+                updates,
+                rebuilds: Vec::new(),
+                helper,
+            })
+        }
+    }
+}
+
+/// Recomputes pending updates after the local package DB changed (e.g. an upgrade from a
+/// shell) without syncing: official updates are re-queried against the sync DBs from the
+/// last check, AUR updates are kept while their installed version is unchanged.
+pub fn recompute_local(
+    config: &EffectiveConfig,
+    previous: &UpdateSnapshot,
+) -> Result<UpdateSnapshot, CommandError> {
+    let limits = RunLimits::new(config.command_timeout);
+    let real_db_path = resolve_pacman_db_path();
+    let local_db = LocalDb::new(&real_db_path);
+    let db_path = checkupdates_db_path();
+    // Snoozed updates are still pending; `PendingHistory::record` hides them again.
+    let mut previous = previous.clone();
+    previous.unsnooze();
+
+    let mut official = if config.official_check_cmd == "auto" && db_path.join("sync").is_dir() {
+        prepare_checkupdates_db(&db_path, &real_db_path)?;
+        let out = query_official_updates(&db_path, &limits)?;
+        let mut official = parse_update_lines(
+            &filter_pacman_qu_output(&out.stdout),
+            UpdateSource::Official,
+        );
+        annotate_sync_info(&db_path, &mut official, &limits);
+        official
     } else {
-        Vec::new()
+        local_db.still_pending(&previous.official)
     };
+    let mut aur = local_db.still_pending(&previous.aur);
+
+    local_db.annotate_reasons(&mut official);
+    local_db.annotate_reasons(&mut aur);
+    let rebuilds = collect_rebuild_hints(config, &local_db, &official, &limits);
 
-    Ok(CheckOutcome {
-        snapshot: UpdateSnapshot { official, aur },
-        helper,
+    Ok(UpdateSnapshot {
+        official,
+        aur,
+        rebuilds,
+        snoozed: Vec::new(),
     })
 }
 
+fn collect_rebuild_hints(
+    config: &EffectiveConfig,
+    local_db: &LocalDb,
+    official: &[PackageUpdate],
+    limits: &RunLimits,
+) -> Vec<RebuildHint> {
+    if official.is_empty() {
+        return Vec::new();
+    }
+
+    let installed = local_db.installed_packages();
+    let foreign = list_foreign_packages(limits);
+    find_rebuild_hints(official, &installed, &foreign, &config.rebuild_watch)
+}
+
+fn list_foreign_packages(limits: &RunLimits) -> HashSet<String> {
+    let cmd = ResolvedCommand {
+        program: "pacman".to_string(),
+        args: vec!["-Qmq".to_string()],
+    };
+
+    match run_capture(&cmd, &[0, 1], limits) {
+        Ok(output) => output
+            .stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(err) => {
+            warn!("failed to list foreign packages ({err}); skipping AUR rebuild hints");
+            HashSet::new()
+        }
+    }
+}
+
 pub fn detect_aur_helper(mode: AurHelperMode, enable_aur: bool) -> Option<DetectedAurHelper> {
     if !enable_aur {
         return None;
@@ -147,40 +351,81 @@ fn is_executable_file(path: &Path) -> bool {
 
 fn run_official_check(
     config: &EffectiveConfig,
-) -> Result<Vec<crate::state::PackageUpdate>, CommandError> {
+    real_db_path: &Path,
+    limits: &RunLimits,
+) -> Result<Vec<PackageUpdate>, CommandError> {
     if config.official_check_cmd != "auto" {
-        return run_official_check_custom(config);
+        return run_official_check_custom(config, limits);
     }
 
     let db_path = checkupdates_db_path();
-    prepare_checkupdates_db(&db_path)?;
+    prepare_checkupdates_db(&db_path, real_db_path)?;
     let _guard = DbLockGuard::new(db_path.join("db.lck"));
 
-    sync_checkupdates_db(&db_path)?;
-    let out = query_official_updates(&db_path)?;
+    sync_checkupdates_db(&db_path, limits)?;
+    let out = query_official_updates(&db_path, limits)?;
     let filtered = filter_pacman_qu_output(&out.stdout);
 
-    Ok(parse_update_lines(&filtered, UpdateSource::Official))
+    let mut updates = parse_update_lines(&filtered, UpdateSource::Official);
+    annotate_sync_info(&db_path, &mut updates, limits);
+    Ok(updates)
+}
+
+/// Fills in repository and download size from the synced checkup DB. Missing info only
+/// leaves the details view with empty cells, so failures are logged and ignored.
+fn annotate_sync_info(db_path: &Path, updates: &mut [PackageUpdate], limits: &RunLimits) {
+    if updates.is_empty() {
+        return;
+    }
+
+    let mut args = vec![
+        "-Si".to_string(),
+        "--dbpath".to_string(),
+        db_path.display().to_string(),
+        "--color".to_string(),
+        "never".to_string(),
+    ];
+    args.extend(updates.iter().map(|update| update.name.clone()));
+    let cmd = ResolvedCommand {
+        program: "pacman".to_string(),
+        args,
+    };
+
+    let info = match run_capture(&cmd, &[0, 1], limits) {
+        Ok(output) => parse_sync_info(&output.stdout),
+        Err(err) => {
+            warn!("failed to read package sizes: {err}");
+            return;
+        }
+    };
+    for update in updates {
+        if let Some(info) = info.get(&update.name) {
+            update.repo = info.repo.clone();
+            update.download_size = info.download_size;
+        }
+    }
 }
 
 fn run_official_check_custom(
     config: &EffectiveConfig,
-) -> Result<Vec<crate::state::PackageUpdate>, CommandError> {
+    limits: &RunLimits,
+) -> Result<Vec<PackageUpdate>, CommandError> {
     let mut cmd = parse_command_string(&config.official_check_cmd)?;
     cmd.args.push("--nocolor".to_string());
-    let out = run_capture(&cmd, &[0, 2])?;
+    let out = run_capture(&cmd, &[0, 2], limits)?;
     Ok(parse_update_lines(&out.stdout, UpdateSource::Official))
 }
 
 fn run_aur_check(
     helper: DetectedAurHelper,
-) -> Result<Vec<crate::state::PackageUpdate>, CommandError> {
+    limits: &RunLimits,
+) -> Result<Vec<PackageUpdate>, CommandError> {
     let cmd = ResolvedCommand {
         program: helper.binary().to_string(),
         args: vec!["-Qua".to_string()],
     };
 
-    let out = run_capture(&cmd, &[0, 1])?;
+    let out = run_capture(&cmd, &[0, 1], limits)?;
     Ok(parse_update_lines(&out.stdout, UpdateSource::Aur))
 }
 
@@ -215,13 +460,12 @@ fn checkupdates_db_path_from_inputs(
     Path::new(tmpdir).join(format!("checkup-db-{uid}"))
 }
 
-fn prepare_checkupdates_db(db_path: &Path) -> Result<(), CommandError> {
+fn prepare_checkupdates_db(db_path: &Path, real_db_path: &Path) -> Result<(), CommandError> {
     fs::create_dir_all(db_path).map_err(|source| CommandError::Io {
         context: format!("create temp pacman db at {}", db_path.display()),
         source,
     })?;
 
-    let real_db_path = resolve_pacman_db_path();
     let src_local = real_db_path.join("local");
     let dst_local = db_path.join("local");
 
@@ -239,13 +483,13 @@ fn prepare_checkupdates_db(db_path: &Path) -> Result<(), CommandError> {
     Ok(())
 }
 
-fn resolve_pacman_db_path() -> PathBuf {
+pub fn resolve_pacman_db_path() -> PathBuf {
     let cmd = ResolvedCommand {
         program: "pacman-conf".to_string(),
         args: vec!["DBPath".to_string()],
     };
 
-    match run_capture(&cmd, &[0]) {
+    match run_capture(&cmd, &[0], &RunLimits::default()) {
         Ok(output) => output
             .stdout
             .lines()
@@ -261,7 +505,7 @@ fn resolve_pacman_db_path() -> PathBuf {
     }
 }
 
-fn sync_checkupdates_db(db_path: &Path) -> Result<(), CommandError> {
+fn sync_checkupdates_db(db_path: &Path, limits: &RunLimits) -> Result<(), CommandError> {
     let cmd = ResolvedCommand {
         program: "fakeroot".to_string(),
         args: vec![
@@ -276,10 +520,13 @@ fn sync_checkupdates_db(db_path: &Path) -> Result<(), CommandError> {
         ],
     };
 
-    run_capture(&cmd, &[0]).map(|_| ())
+    run_capture(&cmd, &[0], limits).map(|_| ())
 }
 
-fn query_official_updates(db_path: &Path) -> Result<CommandOutput, CommandError> {
+fn query_official_updates(
+    db_path: &Path,
+    limits: &RunLimits,
+) -> Result<CommandOutput, CommandError> {
     let cmd = ResolvedCommand {
         program: "pacman".to_string(),
         args: vec![
@@ -291,7 +538,7 @@ fn query_official_updates(db_path: &Path) -> Result<CommandOutput, CommandError>
         ],
     };
 
-    run_capture(&cmd, &[0, 1])
+    run_capture(&cmd, &[0, 1], limits)
 }
 
 fn filter_pacman_qu_output(stdout: &str) -> String {
@@ -299,7 +546,8 @@ fn filter_pacman_qu_output(stdout: &str) -> String {
         .lines()
         .filter(|line| {
             let trimmed = line.trim();
-            !trimmed.is_empty() && !(trimmed.contains('[') && trimmed.contains(']'))
+            let bracketed = trimmed.contains('[') && trimmed.contains(']');
+            !trimmed.is_empty() && !bracketed
         })
         .collect::<Vec<_>>()
         .join("\n")
@@ -341,21 +589,73 @@ struct CommandOutput {
     _stderr: String,
 }
 
+/// Runs `cmd` in its own process group with stdin closed. The whole group is killed when
+/// `limits.timeout` passes or `limits.cancel` is set, so helpers spawned by the command
+/// (e.g. `pacman` under `fakeroot`) do not outlive it.
 fn run_capture(
     cmd: &ResolvedCommand,
     allowed_codes: &[i32],
+    limits: &RunLimits,
 ) -> Result<CommandOutput, CommandError> {
-    let output = Command::new(&cmd.program)
+    if limits.cancel.is_cancelled() {
+        return Err(CommandError::Cancelled {
+            command: shell_join(&cmd.program, &cmd.args),
+        });
+    }
+
+    let mut child = Command::new(&cmd.program)
         .args(&cmd.args)
-        .output()
+        // Output is parsed, so keep pacman's field names and messages untranslated.
+        .env("LC_ALL", "C")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
         .map_err(|source| CommandError::Spawn {
             program: cmd.program.clone(),
             source,
         })?;
 
-    let status = output.status;
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let stdout_reader = child.stdout.take().map(spawn_pipe_reader);
+    let stderr_reader = child.stderr.take().map(spawn_pipe_reader);
+
+    let deadline = Instant::now() + limits.timeout;
+    let status = loop {
+        let polled = child.try_wait().map_err(|source| CommandError::Io {
+            context: format!("waiting for `{}`", cmd.program),
+            source,
+        })?;
+        if let Some(status) = polled {
+            break status;
+        }
+
+        let cancelled = limits.cancel.is_cancelled();
+        if cancelled || Instant::now() >= deadline {
+            kill_process_group(&mut child);
+            let command = shell_join(&cmd.program, &cmd.args);
+            return Err(if cancelled {
+                CommandError::Cancelled { command }
+            } else {
+                CommandError::Timeout {
+                    command,
+                    seconds: limits.timeout.as_secs(),
+                }
+            });
+        }
+
+        thread::sleep(CHILD_POLL_INTERVAL);
+    };
+
+    let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+        reader
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+    let stdout = String::from_utf8_lossy(&collect(stdout_reader)).to_string();
+    let stderr = String::from_utf8_lossy(&collect(stderr_reader))
+        .trim()
+        .to_string();
 
     if is_allowed(status, allowed_codes) {
         return Ok(CommandOutput {
@@ -375,6 +675,24 @@ fn run_capture(
     })
 }
 
+fn spawn_pipe_reader(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+fn kill_process_group(child: &mut Child) {
+    // The child leads its own group (`process_group(0)`), so its pid is the group id.
+    let pgid = child.id() as libc::pid_t;
+    // SAFETY: killpg only sends a signal; a stale group id at worst fails with ESRCH.
+    if unsafe { libc::killpg(pgid, libc::SIGKILL) } != 0 {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+}
+
 fn is_allowed(status: ExitStatus, allowed_codes: &[i32]) -> bool {
     status
         .code()
@@ -389,35 +707,6 @@ fn shell_join(program: &str, args: &[String]) -> String {
     all.join(" ")
 }
 
-pub fn build_details_shell_command(
-    config: &EffectiveConfig,
-    helper: Option<DetectedAurHelper>,
-) -> Result<String, CommandError> {
-    let mut pieces: Vec<String> = Vec::new();
-
-    if config.official_check_cmd == "auto" {
-        pieces.push("pacman -Qu --color never".to_string());
-    } else {
-        let mut official = parse_command_string(&config.official_check_cmd)?;
-        official.args.push("--nocolor".to_string());
-        pieces.push(shell_join(&official.program, &official.args));
-    }
-
-    if config.enable_aur {
-        if let Some(h) = helper {
-            pieces.push("echo".to_string());
-            pieces.push(format!("{} -Qua", h.binary()));
-        } else {
-            pieces.push("echo".to_string());
-            pieces.push("echo 'AUR helper not found (expected paru or yay)'".to_string());
-        }
-    }
-
-    pieces.push("echo".to_string());
-    pieces.push("read -n 1 -s -r -p 'Press any key to close...'".to_string());
-    Ok(pieces.join("; "))
-}
-
 pub fn build_upgrade_shell_command(
     config: &EffectiveConfig,
     helper: Option<DetectedAurHelper>,
@@ -440,14 +729,6 @@ pub fn build_upgrade_aur_shell_command(helper: Option<DetectedAurHelper>) -> Opt
     helper.map(|h| format!("{} -Sua", h.binary()))
 }
 
-pub fn launch_in_terminal(
-    config: &EffectiveConfig,
-    shell_command: &str,
-) -> Result<(), CommandError> {
-    launch_in_terminal_process(config, shell_command)?;
-    Ok(())
-}
-
 pub fn launch_in_terminal_process(
     config: &EffectiveConfig,
     shell_command: &str,
@@ -592,6 +873,63 @@ mod tests {
         assert_eq!(path, PathBuf::from("/tmpx/checkup-db-1234"));
     }
 
+    fn shell(script: &str) -> ResolvedCommand {
+        ResolvedCommand {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+        }
+    }
+
+    #[test]
+    fn timeout_kills_the_whole_process_group() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let pid_file = temp.path().join("pid");
+        let cmd = shell(&format!(
+            "sleep 30 & echo $! > {}; wait",
+            pid_file.display()
+        ));
+        let limits = RunLimits::new(Duration::from_millis(300));
+
+        let started = Instant::now();
+        let err = run_capture(&cmd, &[0], &limits).expect_err("should time out");
+        assert!(matches!(err, CommandError::Timeout { .. }), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let pid: libc::pid_t = fs::read_to_string(&pid_file)
+            .expect("read pid")
+            .trim()
+            .parse()
+            .expect("parse pid");
+        // Killed but not yet reaped by whoever inherited it still counts as gone.
+        let gone = (0..50).any(|_| {
+            let alive = fs::read_to_string(format!("/proc/{pid}/stat"))
+                .is_ok_and(|stat| !stat.contains(") Z "));
+            thread::sleep(Duration::from_millis(20));
+            !alive
+        });
+        assert!(gone, "background sleep survived the timeout");
+    }
+
+    #[test]
+    fn cancel_aborts_a_running_command() {
+        let limits = RunLimits::new(Duration::from_secs(30));
+        let cancel = limits.cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            cancel.cancel();
+        });
+
+        let started = Instant::now();
+        let err = run_capture(&shell("sleep 30"), &[0], &limits).expect_err("should cancel");
+        assert!(err.is_cancelled(), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let err = run_capture(&shell("true"), &[0], &limits).expect_err("still cancelled");
+        assert!(err.is_cancelled());
+        let fresh = RunLimits::new(Duration::from_secs(30));
+        assert!(run_capture(&shell("echo ok"), &[0], &fresh).is_ok());
+    }
+
     #[test]
     fn filter_pacman_qu_output_drops_bracket_lines() {
         let input = "pacman 1.0-1 -> 1.0-2\nwarning: [ignored package]\nopenssl 3.1-1 -> 3.1-2\n";
@@ -1,45 +1,112 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use serde::Deserialize;
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+use crate::commands::DEFAULT_COMMAND_TIMEOUT;
+use crate::icons::IconOverrides;
+use crate::power::PowerPolicy;
+use crate::retry::RetryPolicy;
+use crate::schedule::{CronExpr, QuietHours, Schedule, ScheduleError};
+use crate::state::UpdateSource;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AurHelperMode {
+    #[default]
     Auto,
     Paru,
     Yay,
     None,
 }
 
-impl Default for AurHelperMode {
-    fn default() -> Self {
-        Self::Auto
-    }
+/// How the tray icon is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrayBackend {
+    /// libayatana-appindicator/libappindicator when installed, otherwise `Sni`.
+    #[default]
+    Auto,
+    AppIndicator,
+    /// Built-in StatusNotifierItem over D-Bus.
+    Sni,
 }
 
 #[derive(Debug, Clone)]
 pub struct EffectiveConfig {
     pub poll_minutes: u64,
     pub notify_on_change: bool,
+    pub notify_on_decrease: bool,
     pub enable_aur: bool,
     pub terminal: String,
     pub official_check_cmd: String,
     pub aur_helper: AurHelperMode,
     pub upgrade_cmd: String,
+    pub rebuild_watch: Vec<String>,
+    pub pending_warn_days: u64,
+    pub schedule: Schedule,
+    /// Separate AUR schedule; `None` means AUR checks follow `schedule`.
+    pub aur_schedule: Option<Schedule>,
+    pub quiet_hours: Vec<QuietHours>,
+    pub retry: RetryPolicy,
+    /// Upper bound for each external command a check runs.
+    pub command_timeout: Duration,
+    pub offline_detection: bool,
+    pub skip_when_metered: bool,
+    pub power: PowerPolicy,
+    pub watch_local_db: bool,
+    /// Draw the pending count onto the tray icon.
+    pub count_badge: bool,
+    /// Spin the built-in icon while a check runs.
+    pub animate_checking: bool,
+    /// Text shown next to the tray icon while updates are pending, e.g. `"{total}"`;
+    /// empty for no label.
+    pub label_template: String,
+    pub tray_backend: TrayBackend,
+    /// `[icons]`: per-status replacements for the default tray icons.
+    pub icons: IconOverrides,
+}
+
+impl EffectiveConfig {
+    pub fn schedule_for(&self, source: UpdateSource) -> &Schedule {
+        match (source, &self.aur_schedule) {
+            (UpdateSource::Aur, Some(schedule)) => schedule,
+            _ => &self.schedule,
+        }
+    }
 }
 
+pub const DEFAULT_REBUILD_WATCH: [&str; 5] = ["glibc", "icu", "openssl", "python", "boost"];
+
 impl Default for EffectiveConfig {
     fn default() -> Self {
         Self {
             poll_minutes: 30,
             notify_on_change: true,
+            notify_on_decrease: false,
             enable_aur: true,
             terminal: "auto".to_string(),
             official_check_cmd: "auto".to_string(),
             aur_helper: AurHelperMode::Auto,
             upgrade_cmd: "auto".to_string(),
+            rebuild_watch: DEFAULT_REBUILD_WATCH.map(String::from).to_vec(),
+            pending_warn_days: 0,
+            schedule: Schedule::every_minutes(30),
+            aur_schedule: None,
+            quiet_hours: Vec::new(),
+            retry: RetryPolicy::default(),
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+            offline_detection: true,
+            skip_when_metered: false,
+            power: PowerPolicy::default(),
+            watch_local_db: true,
+            count_badge: false,
+            animate_checking: true,
+            label_template: String::new(),
+            tray_backend: TrayBackend::Auto,
+            icons: IconOverrides::default(),
         }
     }
 }
@@ -54,11 +121,50 @@ pub struct CliOverrides {
 struct FileConfig {
     poll_minutes: Option<u64>,
     notify_on_change: Option<bool>,
+    notify_on_decrease: Option<bool>,
     enable_aur: Option<bool>,
     terminal: Option<String>,
     official_check_cmd: Option<String>,
     aur_helper: Option<AurHelperMode>,
     upgrade_cmd: Option<String>,
+    rebuild_watch: Option<Vec<String>>,
+    pending_warn_days: Option<u64>,
+    schedule: Option<FileSchedule>,
+    aur_poll_minutes: Option<u64>,
+    aur_schedule: Option<FileSchedule>,
+    quiet_hours: Option<Vec<String>>,
+    retry_attempts: Option<u32>,
+    retry_base_seconds: Option<u64>,
+    retry_degraded_minutes: Option<u64>,
+    command_timeout_seconds: Option<u64>,
+    offline_detection: Option<bool>,
+    skip_when_metered: Option<bool>,
+    battery_threshold: Option<u8>,
+    battery_poll_factor: Option<u32>,
+    battery_skip_aur: Option<bool>,
+    watch_local_db: Option<bool>,
+    count_badge: Option<bool>,
+    animate_checking: Option<bool>,
+    label_template: Option<String>,
+    tray_backend: Option<TrayBackend>,
+    icons: Option<IconOverrides>,
+}
+
+/// `schedule = "0 */2 * * *"` or `schedule = ["08:00", "18:30"]`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FileSchedule {
+    Cron(String),
+    Daily(Vec<String>),
+}
+
+impl FileSchedule {
+    fn parse(self) -> Result<Schedule, ScheduleError> {
+        match self {
+            Self::Cron(raw) => Ok(Schedule::Cron(raw.parse::<CronExpr>()?)),
+            Self::Daily(times) => Schedule::daily(&times),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -73,6 +179,24 @@ pub enum ConfigError {
         path: PathBuf,
         source: toml::de::Error,
     },
+    #[error("invalid config at {path}: {source}")]
+    Invalid {
+        path: PathBuf,
+        source: ScheduleError,
+    },
+}
+
+/// Where the running config came from, so it can be loaded again after the file changes.
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub overrides: CliOverrides,
+}
+
+impl ConfigSource {
+    pub fn load(&self) -> Result<EffectiveConfig, ConfigError> {
+        load_config(Some(self.path.clone()), &self.overrides).map(|(config, _)| config)
+    }
 }
 
 pub fn default_config_path() -> PathBuf {
@@ -87,14 +211,33 @@ pub fn load_config(
     let path = path_override.unwrap_or_else(default_config_path);
     let from_file = read_file_config(&path)?;
 
+    let mut merged = merge_file_config(from_file, &path)?;
+
+    if let Some(v) = cli.poll_minutes {
+        merged.poll_minutes = v.max(1);
+        merged.schedule = Schedule::every_minutes(merged.poll_minutes);
+    }
+    if cli.no_aur {
+        merged.enable_aur = false;
+    }
+
+    Ok((merged, path))
+}
+
+/// Applies the keys set in a config file on top of the defaults.
+fn merge_file_config(from_file: FileConfig, path: &Path) -> Result<EffectiveConfig, ConfigError> {
     let mut merged = EffectiveConfig::default();
 
     if let Some(v) = from_file.poll_minutes {
         merged.poll_minutes = v.max(1);
+        merged.schedule = Schedule::every_minutes(merged.poll_minutes);
     }
     if let Some(v) = from_file.notify_on_change {
         merged.notify_on_change = v;
     }
+    if let Some(v) = from_file.notify_on_decrease {
+        merged.notify_on_decrease = v;
+    }
     if let Some(v) = from_file.enable_aur {
         merged.enable_aur = v;
     }
@@ -110,15 +253,94 @@ pub fn load_config(
     if let Some(v) = from_file.upgrade_cmd {
         merged.upgrade_cmd = v;
     }
+    if let Some(v) = from_file.rebuild_watch {
+        merged.rebuild_watch = v;
+    }
+    if let Some(v) = from_file.pending_warn_days {
+        merged.pending_warn_days = v;
+    }
+    if let Some(v) = from_file.retry_attempts {
+        merged.retry.fast_retries = v;
+    }
+    if let Some(v) = from_file.retry_base_seconds {
+        merged.retry.base_delay = Duration::from_secs(v.max(1));
+    }
+    if let Some(v) = from_file.retry_degraded_minutes {
+        merged.retry.degraded_interval = Duration::from_secs(v.max(1).saturating_mul(60));
+        // A delay configured above the usual cap is used as is rather than cut back to it.
+        merged.retry.max_degraded_interval = merged
+            .retry
+            .max_degraded_interval
+            .max(merged.retry.degraded_interval);
+    }
+    if let Some(v) = from_file.command_timeout_seconds {
+        merged.command_timeout = Duration::from_secs(v.max(1));
+    }
+    if let Some(v) = from_file.offline_detection {
+        merged.offline_detection = v;
+    }
+    if let Some(v) = from_file.skip_when_metered {
+        merged.skip_when_metered = v;
+    }
+    if let Some(v) = from_file.battery_threshold {
+        merged.power.threshold = v.min(100);
+    }
+    if let Some(v) = from_file.battery_poll_factor {
+        merged.power.poll_factor = v.max(1);
+    }
+    if let Some(v) = from_file.battery_skip_aur {
+        merged.power.skip_aur = v;
+    }
+    if let Some(v) = from_file.watch_local_db {
+        merged.watch_local_db = v;
+    }
+    if let Some(v) = from_file.count_badge {
+        merged.count_badge = v;
+    }
+    if let Some(v) = from_file.animate_checking {
+        merged.animate_checking = v;
+    }
+    if let Some(v) = from_file.label_template {
+        merged.label_template = v;
+    }
+    if let Some(v) = from_file.tray_backend {
+        merged.tray_backend = v;
+    }
+    if let Some(v) = from_file.icons {
+        merged.icons = v;
+    }
 
-    if let Some(v) = cli.poll_minutes {
-        merged.poll_minutes = v.max(1);
+    let invalid = |source| ConfigError::Invalid {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(raw) = from_file.schedule {
+        merged.schedule = raw.parse().map_err(invalid)?;
     }
-    if cli.no_aur {
-        merged.enable_aur = false;
+    if let Some(v) = from_file.aur_poll_minutes {
+        merged.aur_schedule = Some(Schedule::every_minutes(v));
+    }
+    if let Some(raw) = from_file.aur_schedule {
+        merged.aur_schedule = Some(raw.parse().map_err(invalid)?);
+    }
+    if let Some(windows) = from_file.quiet_hours {
+        merged.quiet_hours = windows
+            .iter()
+            .map(|raw| raw.parse::<QuietHours>())
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?;
     }
 
-    Ok((merged, path))
+    Ok(merged)
+}
+
+/// Checks config file text the way `load_config` reads it, without command-line overrides.
+pub fn parse_config(raw: &str, path: &Path) -> Result<EffectiveConfig, ConfigError> {
+    let from_file = toml::from_str(raw).map_err(|source| ConfigError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    merge_file_config(from_file, path)
 }
 
 fn read_file_config(path: &Path) -> Result<FileConfig, ConfigError> {
@@ -142,6 +364,7 @@ mod tests {
     use std::fs;
 
     use super::*;
+    use crate::state::Status;
 
     #[test]
     fn config_merging_precedence_defaults_file_cli() {
@@ -150,7 +373,8 @@ mod tests {
 
         fs::write(
             &cfg_path,
-            "poll_minutes = 45\nenable_aur = true\nnotify_on_change = false\naur_helper = \"paru\"\n",
+            "poll_minutes = 45\nenable_aur = true\nnotify_on_change = false\naur_helper = \"paru\"\n\
+             tray_backend = \"sni\"\n",
         )
         .expect("write config");
 
@@ -164,6 +388,7 @@ mod tests {
         assert!(!cfg.enable_aur);
         assert!(!cfg.notify_on_change);
         assert_eq!(cfg.aur_helper, AurHelperMode::Paru);
+        assert_eq!(cfg.tray_backend, TrayBackend::Sni);
     }
 
     #[test]
@@ -176,5 +401,155 @@ mod tests {
         assert!(cfg.notify_on_change);
         assert!(cfg.enable_aur);
         assert_eq!(cfg.aur_helper, AurHelperMode::Auto);
+        assert_eq!(cfg.rebuild_watch.len(), DEFAULT_REBUILD_WATCH.len());
+        assert_eq!(cfg.schedule, Schedule::every_minutes(30));
+        assert!(cfg.quiet_hours.is_empty());
+    }
+
+    #[test]
+    fn long_degraded_delays_are_not_capped_and_do_not_overflow() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let cfg_path = temp.path().join("config.toml");
+
+        fs::write(&cfg_path, "retry_degraded_minutes = 120\n").expect("write config");
+        let (cfg, _) = load_config(Some(cfg_path.clone()), &CliOverrides::default()).expect("load");
+        assert_eq!(cfg.retry.degraded_delay(1), Duration::from_secs(120 * 60));
+        assert_eq!(cfg.retry.degraded_delay(3), Duration::from_secs(120 * 60));
+
+        fs::write(
+            &cfg_path,
+            format!("retry_degraded_minutes = {}\n", i64::MAX),
+        )
+        .expect("write");
+        let (cfg, _) = load_config(Some(cfg_path), &CliOverrides::default()).expect("load");
+        assert_eq!(cfg.retry.degraded_interval, Duration::from_secs(u64::MAX));
+    }
+
+    #[test]
+    fn parses_cron_and_daily_schedules() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let cfg_path = temp.path().join("config.toml");
+
+        fs::write(
+            &cfg_path,
+            "schedule = \"0 */2 * * *\"\nquiet_hours = [\"22:00-07:00\"]\n",
+        )
+        .expect("write config");
+        let (cfg, _) = load_config(Some(cfg_path.clone()), &CliOverrides::default()).expect("load");
+        assert!(matches!(cfg.schedule, Schedule::Cron(_)));
+        assert_eq!(cfg.quiet_hours.len(), 1);
+
+        fs::write(&cfg_path, "schedule = [\"08:00\", \"18:30\"]\n").expect("write config");
+        let (cfg, _) = load_config(Some(cfg_path.clone()), &CliOverrides::default()).expect("load");
+        assert!(matches!(cfg.schedule, Schedule::Daily(ref times) if times.len() == 2));
+
+        let cli = CliOverrides {
+            poll_minutes: Some(5),
+            no_aur: false,
+        };
+        let (cfg, _) = load_config(Some(cfg_path), &cli).expect("load");
+        assert_eq!(cfg.schedule, Schedule::every_minutes(5));
+    }
+
+    #[test]
+    fn aur_schedule_defaults_to_main_schedule() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let cfg_path = temp.path().join("config.toml");
+
+        fs::write(
+            &cfg_path,
+            "poll_minutes = 30
+",
+        )
+        .expect("write config");
+        let (cfg, _) = load_config(Some(cfg_path.clone()), &CliOverrides::default()).expect("load");
+        assert_eq!(
+            cfg.schedule_for(UpdateSource::Aur),
+            &Schedule::every_minutes(30)
+        );
+
+        fs::write(&cfg_path, "poll_minutes = 30\naur_poll_minutes = 360\n").expect("write config");
+        let (cfg, _) = load_config(Some(cfg_path), &CliOverrides::default()).expect("load");
+        assert_eq!(
+            cfg.schedule_for(UpdateSource::Official),
+            &Schedule::every_minutes(30)
+        );
+        assert_eq!(
+            cfg.schedule_for(UpdateSource::Aur),
+            &Schedule::every_minutes(360)
+        );
+    }
+
+    #[test]
+    fn invalid_schedule_is_reported() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let cfg_path = temp.path().join("config.toml");
+        fs::write(&cfg_path, "schedule = \"every two hours\"\n").expect("write config");
+
+        let err = load_config(Some(cfg_path), &CliOverrides::default()).expect_err("invalid");
+        assert!(matches!(err, ConfigError::Invalid { .. }));
+    }
+
+    #[test]
+    fn reloading_keeps_cli_overrides_on_top_of_edits() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let source = ConfigSource {
+            path: temp.path().join("config.toml"),
+            overrides: CliOverrides {
+                poll_minutes: None,
+                no_aur: true,
+            },
+        };
+
+        fs::write(
+            &source.path,
+            "poll_minutes = 45
+",
+        )
+        .expect("write config");
+        let cfg = source.load().expect("load");
+        assert_eq!(cfg.poll_minutes, 45);
+        assert!(!cfg.enable_aur);
+
+        fs::write(
+            &source.path,
+            "poll_minutes = 15
+enable_aur = true
+",
+        )
+        .expect("write config");
+        let cfg = source.load().expect("reload");
+        assert_eq!(cfg.poll_minutes, 15);
+        assert!(!cfg.enable_aur);
+
+        fs::write(&source.path, "poll_minutes = \"soon\"\n").expect("write config");
+        assert!(matches!(source.load(), Err(ConfigError::Parse { .. })));
+    }
+
+    #[test]
+    fn icons_table_accepts_names_and_variants() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let cfg_path = temp.path().join("config.toml");
+        fs::write(
+            &cfg_path,
+            "[icons]\nup_to_date = \"emblem-ok\"\n\
+             error = { light = \"/icons/error.svg\", dark = \"/icons/error-dark.svg\" }\n",
+        )
+        .expect("write config");
+
+        let (cfg, _) = load_config(Some(cfg_path), &CliOverrides::default()).expect("load");
+        assert_eq!(
+            cfg.icons
+                .get(&Status::UpToDate)
+                .and_then(|spec| spec.pick(true)),
+            Some("emblem-ok")
+        );
+        assert_eq!(
+            cfg.icons
+                .get(&Status::Error)
+                .and_then(|spec| spec.pick(true)),
+            Some("/icons/error-dark.svg")
+        );
+        assert_eq!(cfg.icons.get(&Status::Offline), None);
     }
 }
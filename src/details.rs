@@ -0,0 +1,289 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+
+use crate::report::format_size;
+use crate::state::{PackageUpdate, UpdateSnapshot, UpdateSource};
+
+const COL_NAME: u32 = 0;
+const COL_SOURCE: u32 = 1;
+const COL_REPO: u32 = 2;
+const COL_CURRENT: u32 = 3;
+const COL_LATEST: u32 = 4;
+const COL_SIZE_TEXT: u32 = 5;
+const COL_SIZE: u32 = 6;
+/// Position of the row's update in `DetailsWindow::updates`.
+const COL_INDEX: u32 = 7;
+
+/// Source filter choices, in combo box order.
+const SOURCE_FILTERS: [&str; 3] = ["All sources", "Official", "AUR"];
+
+/// Window listing the pending updates of the last check, with search, a source filter,
+/// sortable columns and actions on the selected packages.
+pub struct DetailsWindow {
+    window: gtk::Window,
+    store: gtk::ListStore,
+    summary: gtk::Label,
+    updates: Rc<RefCell<Vec<PackageUpdate>>>,
+}
+
+impl DetailsWindow {
+    /// `on_upgrade` receives the selected updates when "Upgrade selected" is clicked.
+    pub fn new(on_upgrade: impl Fn(Vec<PackageUpdate>) + 'static) -> Self {
+        let window = gtk::Window::new(gtk::WindowType::Toplevel);
+        window.set_title("Pending updates");
+        window.set_default_size(720, 480);
+        window.connect_delete_event(|window, _| {
+            window.hide();
+            glib::Propagation::Stop
+        });
+
+        let store = gtk::ListStore::new(&[
+            glib::Type::STRING,
+            glib::Type::STRING,
+            glib::Type::STRING,
+            glib::Type::STRING,
+            glib::Type::STRING,
+            glib::Type::STRING,
+            glib::Type::U64,
+            glib::Type::U32,
+        ]);
+
+        let search = gtk::SearchEntry::new();
+        search.set_hexpand(true);
+        let source_filter = gtk::ComboBoxText::new();
+        for label in SOURCE_FILTERS {
+            source_filter.append_text(label);
+        }
+        source_filter.set_active(Some(0));
+
+        let filter = gtk::TreeModelFilter::new(&store, None);
+        {
+            let (search, source_filter) = (search.clone(), source_filter.clone());
+            filter.set_visible_func(move |model, iter| {
+                let name = text_at(model, iter, COL_NAME);
+                let source = text_at(model, iter, COL_SOURCE);
+                let query = search.text().to_lowercase();
+                let source_matches = match source_filter.active() {
+                    Some(index) if index > 0 => SOURCE_FILTERS[index as usize] == source,
+                    _ => true,
+                };
+                source_matches && name.to_lowercase().contains(&query)
+            });
+        }
+        {
+            let filter = filter.clone();
+            search.connect_search_changed(move |_| filter.refilter());
+        }
+        {
+            let filter = filter.clone();
+            source_filter.connect_changed(move |_| filter.refilter());
+        }
+
+        let sorted = gtk::TreeModelSort::new(&filter);
+        sorted.set_sort_column_id(gtk::SortColumn::Index(COL_NAME), gtk::SortType::Ascending);
+
+        let view = gtk::TreeView::with_model(&sorted);
+        view.selection().set_mode(gtk::SelectionMode::Multiple);
+        for (title, text_col, sort_col) in [
+            ("Name", COL_NAME, COL_NAME),
+            ("Source", COL_SOURCE, COL_SOURCE),
+            ("Repository", COL_REPO, COL_REPO),
+            ("Installed", COL_CURRENT, COL_CURRENT),
+            ("Available", COL_LATEST, COL_LATEST),
+            ("Size", COL_SIZE_TEXT, COL_SIZE),
+        ] {
+            let cell = gtk::CellRendererText::new();
+            let column = gtk::TreeViewColumn::new();
+            column.set_title(title);
+            column.set_resizable(true);
+            CellLayoutExt::pack_start(&column, &cell, true);
+            CellLayoutExt::add_attribute(&column, &cell, "text", text_col as i32);
+            column.set_sort_column_id(sort_col as i32);
+            view.append_column(&column);
+        }
+
+        let scrolled = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        scrolled.set_vexpand(true);
+        scrolled.add(&view);
+
+        let updates: Rc<RefCell<Vec<PackageUpdate>>> = Rc::default();
+
+        let upgrade_button = gtk::Button::with_label("Upgrade selected");
+        upgrade_button.set_sensitive(false);
+        {
+            let upgrade_button = upgrade_button.clone();
+            view.selection().connect_changed(move |selection| {
+                upgrade_button.set_sensitive(selection.count_selected_rows() > 0);
+            });
+        }
+        {
+            let (view, updates) = (view.clone(), Rc::clone(&updates));
+            upgrade_button.connect_clicked(move |_| {
+                let selected = selected_updates(&view, &updates.borrow());
+                if !selected.is_empty() {
+                    on_upgrade(selected);
+                }
+            });
+        }
+
+        let copy_button = gtk::Button::with_label("Copy list");
+        copy_button.set_tooltip_text(Some("Copies the selected rows, or every listed row"));
+        {
+            let (view, updates) = (view.clone(), Rc::clone(&updates));
+            copy_button.connect_clicked(move |_| {
+                let updates = updates.borrow();
+                let mut rows = selected_updates(&view, &updates);
+                if rows.is_empty() {
+                    rows = visible_updates(&view, &updates);
+                }
+                let text: Vec<String> = rows
+                    .iter()
+                    .map(|update| {
+                        format!("{} {} -> {}", update.name, update.current, update.latest)
+                    })
+                    .collect();
+                gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD).set_text(&text.join("\n"));
+            });
+        }
+
+        let close_button = gtk::Button::with_label("Close");
+        {
+            let window = window.clone();
+            close_button.connect_clicked(move |_| window.hide());
+        }
+
+        let summary = gtk::Label::new(None);
+        summary.set_xalign(0.0);
+        summary.set_hexpand(true);
+
+        let filters = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        filters.pack_start(&search, true, true, 0);
+        filters.pack_start(&source_filter, false, false, 0);
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.pack_start(&summary, true, true, 0);
+        buttons.pack_end(&close_button, false, false, 0);
+        buttons.pack_end(&copy_button, false, false, 0);
+        buttons.pack_end(&upgrade_button, false, false, 0);
+
+        let layout = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        layout.set_border_width(8);
+        layout.pack_start(&filters, false, false, 0);
+        layout.pack_start(&scrolled, true, true, 0);
+        layout.pack_start(&buttons, false, false, 0);
+        window.add(&layout);
+
+        Self {
+            window,
+            store,
+            summary,
+            updates,
+        }
+    }
+
+    /// Fills the window from `snapshot` and brings it to the front.
+    pub fn present(&self, snapshot: Option<&UpdateSnapshot>) {
+        self.set_snapshot(snapshot);
+        self.window.show_all();
+        self.window.present();
+    }
+
+    /// Replaces the listed updates; keeps the search text, filter and sort order.
+    pub fn set_snapshot(&self, snapshot: Option<&UpdateSnapshot>) {
+        let updates: Vec<PackageUpdate> = snapshot
+            .map(|snapshot| snapshot.all().cloned().collect())
+            .unwrap_or_default();
+
+        self.store.clear();
+        for (index, update) in updates.iter().enumerate() {
+            let size_text = update.download_size.map(format_size).unwrap_or_default();
+            self.store.insert_with_values(
+                None,
+                &[
+                    (COL_NAME, &update.name),
+                    (COL_SOURCE, &update.source.label()),
+                    (COL_REPO, &repo_label(update)),
+                    (COL_CURRENT, &update.current),
+                    (COL_LATEST, &update.latest),
+                    (COL_SIZE_TEXT, &size_text),
+                    (COL_SIZE, &update.download_size.unwrap_or(0)),
+                    (COL_INDEX, &(index as u32)),
+                ],
+            );
+        }
+
+        self.summary.set_text(&summary_text(snapshot, &updates));
+        *self.updates.borrow_mut() = updates;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.window.is_visible()
+    }
+}
+
+fn repo_label(update: &PackageUpdate) -> String {
+    match (update.source, &update.repo) {
+        (UpdateSource::Aur, _) => "aur".to_string(),
+        (UpdateSource::Official, Some(repo)) => repo.clone(),
+        (UpdateSource::Official, None) => String::new(),
+    }
+}
+
+fn summary_text(snapshot: Option<&UpdateSnapshot>, updates: &[PackageUpdate]) -> String {
+    let Some(snapshot) = snapshot else {
+        return "No completed update check yet.".to_string();
+    };
+
+    let download: u64 = updates
+        .iter()
+        .filter_map(|update| update.download_size)
+        .sum();
+    let mut text = format!("{} pending", updates.len());
+    if download > 0 {
+        text.push_str(&format!(", {} to download", format_size(download)));
+    }
+    if !snapshot.snoozed.is_empty() {
+        text.push_str(&format!(", {} snoozed", snapshot.snoozed.len()));
+    }
+    text
+}
+
+fn row_update(
+    model: &gtk::TreeModel,
+    iter: &gtk::TreeIter,
+    updates: &[PackageUpdate],
+) -> Option<PackageUpdate> {
+    let index = model.value(iter, COL_INDEX as i32).get::<u32>().ok()?;
+    updates.get(index as usize).cloned()
+}
+
+fn text_at(model: &gtk::TreeModel, iter: &gtk::TreeIter, column: u32) -> String {
+    model
+        .value(iter, column as i32)
+        .get::<String>()
+        .unwrap_or_default()
+}
+
+fn selected_updates(view: &gtk::TreeView, updates: &[PackageUpdate]) -> Vec<PackageUpdate> {
+    let (paths, model) = view.selection().selected_rows();
+    paths
+        .iter()
+        .filter_map(|path| model.iter(path))
+        .filter_map(|iter| row_update(&model, &iter, updates))
+        .collect()
+}
+
+/// Rows left by the search and filter, in display order.
+fn visible_updates(view: &gtk::TreeView, updates: &[PackageUpdate]) -> Vec<PackageUpdate> {
+    let Some(model) = view.model() else {
+        return Vec::new();
+    };
+    let mut rows = Vec::new();
+    model.foreach(|model, _, iter| {
+        rows.extend(row_update(model, iter, updates));
+        false
+    });
+    rows
+}
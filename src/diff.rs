@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use crate::state::{PackageUpdate, UpdateSnapshot, UpdateSource};
+
+/// Differences between two consecutive snapshots, keyed by source and package name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// Packages that were not pending before.
+    pub added: Vec<PackageUpdate>,
+    /// Packages that are no longer pending (upgraded, removed or ignored).
+    pub removed: Vec<PackageUpdate>,
+    /// Packages that were already pending and now have an even newer version.
+    pub bumped: Vec<PackageUpdate>,
+}
+
+impl SnapshotDiff {
+    pub fn between(previous: &UpdateSnapshot, current: &UpdateSnapshot) -> Self {
+        let before: HashMap<(UpdateSource, &str), &PackageUpdate> = previous
+            .all()
+            .map(|update| ((update.source, update.name.as_str()), update))
+            .collect();
+        let after: HashMap<(UpdateSource, &str), &PackageUpdate> = current
+            .all()
+            .map(|update| ((update.source, update.name.as_str()), update))
+            .collect();
+
+        let mut diff = Self::default();
+        for update in current.all() {
+            match before.get(&(update.source, update.name.as_str())) {
+                None => diff.added.push(update.clone()),
+                Some(old) if old.latest != update.latest => diff.bumped.push(update.clone()),
+                Some(_) => {}
+            }
+        }
+
+        diff.removed = previous
+            .all()
+            .filter(|update| !after.contains_key(&(update.source, update.name.as_str())))
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    /// Updates that arrived since the previous snapshot: new packages and new versions.
+    pub fn arrivals(&self) -> impl Iterator<Item = &PackageUpdate> {
+        self.added.iter().chain(self.bumped.iter())
+    }
+
+    pub fn has_arrivals(&self) -> bool {
+        !self.added.is_empty() || !self.bumped.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.has_arrivals() && self.removed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(official: &[(&str, &str)], aur: &[(&str, &str)]) -> UpdateSnapshot {
+        let build = |items: &[(&str, &str)], source| {
+            items
+                .iter()
+                .map(|(name, latest)| PackageUpdate::new(name, "1-1", latest, source))
+                .collect()
+        };
+
+        UpdateSnapshot {
+            official: build(official, UpdateSource::Official),
+            aur: build(aur, UpdateSource::Aur),
+            ..UpdateSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn classifies_added_removed_and_bumped() {
+        let previous = snapshot(
+            &[("firefox", "2-1"), ("icu", "2-1")],
+            &[("foo-git", "r1-1")],
+        );
+        let current = snapshot(
+            &[("firefox", "3-1"), ("neovim", "2-1")],
+            &[("foo-git", "r1-1")],
+        );
+
+        let diff = SnapshotDiff::between(&previous, &current);
+
+        let names =
+            |list: &[PackageUpdate]| list.iter().map(|u| u.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&diff.added), vec!["neovim"]);
+        assert_eq!(names(&diff.bumped), vec!["firefox"]);
+        assert_eq!(names(&diff.removed), vec!["icu"]);
+    }
+
+    #[test]
+    fn same_name_in_different_sources_is_distinct() {
+        let previous = snapshot(&[("foo", "2-1")], &[]);
+        let current = snapshot(&[], &[("foo", "2-1")]);
+
+        let diff = SnapshotDiff::between(&previous, &current);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.removed.len(), 1);
+        assert!(diff.bumped.is_empty());
+    }
+
+    #[test]
+    fn identical_snapshots_produce_empty_diff() {
+        let current = snapshot(&[("firefox", "2-1")], &[]);
+        assert!(SnapshotDiff::between(&current, &current).is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::FromRawFd;
+use std::os::raw::{c_int, c_void};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use log::{debug, info, warn};
+
+use crate::config::{ConfigSource, EffectiveConfig};
+use crate::diff::SnapshotDiff;
+use crate::notifier::{self, Announcer};
+use crate::report::status_text;
+use crate::scheduler::{SchedulerCommand, SchedulerUpdate, start_scheduler};
+use crate::state::UpdateSnapshot;
+
+/// How often held-back notifications are retried while no results arrive.
+const ANNOUNCE_POLL: Duration = Duration::from_secs(60);
+
+/// Write end of the pipe the signal handler reports to; -1 until handlers are installed.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Runs the scheduler without a tray, printing status changes and new updates to stdout and
+/// sending the usual desktop notifications. Returns after SIGTERM or SIGINT, once running
+/// checks are cancelled and the scheduler has stopped.
+pub fn run(config: EffectiveConfig, config_source: ConfigSource) -> Result<(), String> {
+    let (updates_tx, updates_rx) = mpsc::channel::<SchedulerUpdate>();
+    let scheduler_tx = start_scheduler(config.clone(), config_source, updates_tx);
+
+    handle_signals(&[libc::SIGTERM, libc::SIGINT], move |signal| {
+        println!("received signal {signal}, shutting down");
+        stop_scheduler(&scheduler_tx);
+    })
+    .map_err(|e| format!("failed to install signal handlers: {e}"))?;
+
+    println!("running headless; status changes and new updates are printed here");
+
+    let mut config = config;
+    let mut last_status = String::new();
+    let mut last_config_error = None;
+    let mut previous: Option<UpdateSnapshot> = None;
+    let mut announcer = Announcer::default();
+
+    loop {
+        let update = match updates_rx.recv_timeout(ANNOUNCE_POLL) {
+            Ok(update) => update,
+            // Announces updates held back by quiet hours once the window ends.
+            Err(RecvTimeoutError::Timeout) => {
+                announce(&mut announcer, &config);
+                continue;
+            }
+            // The scheduler stopped and dropped its sender.
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if let Some(new_config) = update.config {
+            info!("applying reloaded config");
+            config = new_config;
+        }
+        if update.config_error != last_config_error {
+            if let Some(error) = &update.config_error {
+                println!("config error, keeping the previous config: {error}");
+            }
+            last_config_error = update.config_error;
+        }
+
+        let status = status_text(&update.state);
+        if status != last_status {
+            println!("status: {status}");
+            last_status = status;
+        }
+
+        let Some(snapshot) = update.snapshot else {
+            continue;
+        };
+        if let Some(previous) = previous.as_ref() {
+            for arrival in SnapshotDiff::between(previous, &snapshot).arrivals() {
+                println!(
+                    "new update: {} {} -> {}",
+                    arrival.name, arrival.current, arrival.latest
+                );
+            }
+        }
+        announcer.observe(&snapshot);
+        announce(&mut announcer, &config);
+        previous = Some(snapshot);
+    }
+
+    println!("stopped");
+    Ok(())
+}
+
+fn announce(announcer: &mut Announcer, config: &EffectiveConfig) {
+    if let Some(body) = announcer.take_message(config, Local::now()) {
+        notifier::notify(&body);
+    }
+}
+
+fn stop_scheduler(scheduler_tx: &Sender<SchedulerCommand>) {
+    if scheduler_tx.send(SchedulerCommand::Cancel).is_err()
+        || scheduler_tx.send(SchedulerCommand::Quit).is_err()
+    {
+        debug!("scheduler already stopped");
+    }
+}
+
+extern "C" fn forward_signal(signal: c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // Only async-signal-safe calls here; the reader thread does the actual work.
+        let byte = signal as u8;
+        unsafe { libc::write(fd, &byte as *const u8 as *const c_void, 1) };
+    }
+}
+
+/// Calls `on_signal` from a helper thread when one of `signals` arrives. A second signal
+/// exits right away, for when a clean shutdown hangs.
+fn handle_signals(
+    signals: &[c_int],
+    on_signal: impl FnOnce(c_int) + Send + 'static,
+) -> io::Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut reader = unsafe { File::from_raw_fd(fds[0]) };
+    SIGNAL_PIPE.store(fds[1], Ordering::Relaxed);
+
+    for &signal in signals {
+        let handler = forward_signal as extern "C" fn(c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    thread::spawn(move || {
+        let mut byte = [0u8];
+        if reader.read_exact(&mut byte).is_err() {
+            warn!("signal pipe closed");
+            return;
+        }
+        on_signal(c_int::from(byte[0]));
+
+        if reader.read_exact(&mut byte).is_ok() {
+            println!("received signal {} again, exiting", byte[0]);
+            std::process::exit(1);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn signals_reach_the_callback() {
+        let (tx, rx) = mpsc::channel();
+        handle_signals(&[libc::SIGUSR2], move |signal| {
+            tx.send(signal).expect("send");
+        })
+        .expect("install handler");
+
+        unsafe { libc::raise(libc::SIGUSR2) };
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(libc::SIGUSR2));
+    }
+}
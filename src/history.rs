@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{PackageUpdate, UpdateSnapshot, UpdateSource};
+
+/// A pending version: its source, name and latest version.
+type PendingKey = (UpdateSource, String, String);
+
+/// First-seen timestamps for every pending version, and the versions the user snoozed,
+/// persisted across runs.
+#[derive(Debug)]
+pub struct PendingHistory {
+    path: PathBuf,
+    first_seen: HashMap<PendingKey, DateTime<Local>>,
+    snoozed_until: HashMap<PendingKey, DateTime<Local>>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct HistoryFile {
+    #[serde(default)]
+    pending: Vec<PendingEntry>,
+    #[serde(default)]
+    snoozed: Vec<SnoozeEntry>,
+}
+
+/// Entries written before the source was recorded apply to both sources.
+#[derive(Debug, Deserialize, Serialize)]
+struct SnoozeEntry {
+    name: String,
+    latest: String,
+    #[serde(default)]
+    source: Option<UpdateSource>,
+    until: DateTime<Local>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PendingEntry {
+    name: String,
+    latest: String,
+    #[serde(default)]
+    source: Option<UpdateSource>,
+    first_seen: DateTime<Local>,
+}
+
+fn entry_keys(
+    source: Option<UpdateSource>,
+    name: String,
+    latest: String,
+) -> impl Iterator<Item = PendingKey> {
+    let sources = match source {
+        Some(source) => vec![source],
+        None => vec![UpdateSource::Official, UpdateSource::Aur],
+    };
+    sources
+        .into_iter()
+        .map(move |source| (source, name.clone(), latest.clone()))
+}
+
+fn update_key(update: &PackageUpdate) -> PendingKey {
+    (update.source, update.name.clone(), update.latest.clone())
+}
+
+pub fn default_history_path() -> PathBuf {
+    let base = dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    base.join("pactrack").join("pending.toml")
+}
+
+impl PendingHistory {
+    pub fn load(path: PathBuf) -> Self {
+        let file = read_history_file(&path).unwrap_or_else(|err| {
+            warn!("ignoring pending history at {}: {err}", path.display());
+            HistoryFile::default()
+        });
+        let first_seen = file
+            .pending
+            .into_iter()
+            .flat_map(|entry| {
+                entry_keys(entry.source, entry.name, entry.latest)
+                    .map(move |key| (key, entry.first_seen))
+            })
+            .collect();
+        let snoozed_until = file
+            .snoozed
+            .into_iter()
+            .flat_map(|entry| {
+                entry_keys(entry.source, entry.name, entry.latest)
+                    .map(move |key| (key, entry.until))
+            })
+            .collect();
+
+        Self {
+            path,
+            first_seen,
+            snoozed_until,
+        }
+    }
+
+    /// Stamps every update in `snapshot` with its first-seen time, moves snoozed updates to
+    /// `snapshot.snoozed` and sorts the lists oldest first. For the sources in `checked`,
+    /// whose lists in `snapshot` are current, versions that are no longer pending are
+    /// forgotten (so a newer version ends a snooze); other sources' entries are kept as is.
+    pub fn record(
+        &mut self,
+        snapshot: &mut UpdateSnapshot,
+        now: DateTime<Local>,
+        checked: &[UpdateSource],
+    ) {
+        snapshot.unsnooze();
+        let mut seen_keys = HashMap::new();
+
+        for update in snapshot.official.iter_mut().chain(snapshot.aur.iter_mut()) {
+            let key = update_key(update);
+            let seen = self.first_seen.get(&key).copied().unwrap_or(now);
+            update.first_seen = Some(seen);
+            seen_keys.insert(key, seen);
+        }
+
+        let pending = |key: &PendingKey| !checked.contains(&key.0) || seen_keys.contains_key(key);
+        self.first_seen.retain(|key, _| pending(key));
+        self.snoozed_until
+            .retain(|key, until| pending(key) && *until > now);
+        self.first_seen
+            .extend(seen_keys.iter().map(|(key, seen)| (key.clone(), *seen)));
+
+        for list in [&mut snapshot.official, &mut snapshot.aur] {
+            let (hidden, shown): (Vec<_>, Vec<_>) = list
+                .drain(..)
+                .partition(|update| self.snoozed_until.contains_key(&update_key(update)));
+            *list = shown;
+            snapshot.snoozed.extend(hidden);
+        }
+
+        sort_by_pending(&mut snapshot.official);
+        sort_by_pending(&mut snapshot.aur);
+        sort_by_pending(&mut snapshot.snoozed);
+    }
+
+    /// Hides `name` at version `latest` from `source` until `until`; the next `record`
+    /// applies it.
+    pub fn snooze(
+        &mut self,
+        source: UpdateSource,
+        name: &str,
+        latest: &str,
+        until: DateTime<Local>,
+    ) {
+        self.snoozed_until
+            .insert((source, name.to_string(), latest.to_string()), until);
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut pending: Vec<PendingEntry> = self
+            .first_seen
+            .iter()
+            .map(|((source, name, latest), first_seen)| PendingEntry {
+                name: name.clone(),
+                latest: latest.clone(),
+                source: Some(*source),
+                first_seen: *first_seen,
+            })
+            .collect();
+        pending.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut snoozed: Vec<SnoozeEntry> = self
+            .snoozed_until
+            .iter()
+            .map(|((source, name, latest), until)| SnoozeEntry {
+                name: name.clone(),
+                latest: latest.clone(),
+                source: Some(*source),
+                until: *until,
+            })
+            .collect();
+        snoozed.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let raw = toml::to_string(&HistoryFile { pending, snoozed })
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, raw)
+    }
+}
+
+fn read_history_file(path: &Path) -> io::Result<HistoryFile> {
+    if !path.exists() {
+        return Ok(HistoryFile::default());
+    }
+
+    let raw = fs::read_to_string(path)?;
+    toml::from_str(&raw).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn sort_by_pending(updates: &mut [PackageUpdate]) {
+    updates.sort_by(|a, b| a.first_seen.cmp(&b.first_seen).then(a.name.cmp(&b.name)));
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    const OFFICIAL: &[UpdateSource] = &[UpdateSource::Official];
+
+    fn snapshot(updates: &[(&str, &str)]) -> UpdateSnapshot {
+        UpdateSnapshot {
+            official: updates
+                .iter()
+                .map(|(name, latest)| {
+                    PackageUpdate::new(name, "1-1", latest, UpdateSource::Official)
+                })
+                .collect(),
+            ..UpdateSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn keeps_first_seen_until_version_changes() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("state").join("pending.toml");
+        let day_one = Local::now() - Duration::days(9);
+        let day_two = Local::now();
+
+        let mut history = PendingHistory::load(path.clone());
+        history.record(
+            &mut snapshot(&[("firefox", "2-1"), ("icu", "2-1")]),
+            day_one,
+            OFFICIAL,
+        );
+        history.save().expect("save history");
+
+        let mut history = PendingHistory::load(path);
+        let mut current = snapshot(&[("icu", "3-1"), ("firefox", "2-1")]);
+        history.record(&mut current, day_two, OFFICIAL);
+
+        assert_eq!(current.official[0].name, "firefox");
+        assert_eq!(current.official[0].pending_days(day_two), Some(9));
+        assert_eq!(current.official[1].pending_days(day_two), Some(0));
+    }
+
+    #[test]
+    fn snooze_hides_a_version_until_it_expires_or_changes() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("pending.toml");
+        let now = Local::now();
+
+        let mut history = PendingHistory::load(path.clone());
+        let mut current = snapshot(&[("firefox", "2-1"), ("icu", "2-1")]);
+        history.record(&mut current, now, OFFICIAL);
+        history.snooze(
+            UpdateSource::Official,
+            "firefox",
+            "2-1",
+            now + Duration::days(7),
+        );
+        history.record(&mut current, now, OFFICIAL);
+        history.save().expect("save history");
+
+        assert_eq!(current.total_count(), 1);
+        assert_eq!(current.snoozed[0].name, "firefox");
+
+        let mut history = PendingHistory::load(path);
+        let mut same = snapshot(&[("firefox", "2-1"), ("icu", "2-1")]);
+        history.record(&mut same, now + Duration::days(1), OFFICIAL);
+        assert_eq!(same.snoozed.len(), 1);
+
+        history.record(&mut same, now + Duration::days(8), OFFICIAL);
+        assert!(same.snoozed.is_empty());
+        assert_eq!(same.total_count(), 2);
+
+        history.snooze(
+            UpdateSource::Official,
+            "firefox",
+            "2-1",
+            now + Duration::days(7),
+        );
+        let mut newer = snapshot(&[("firefox", "3-1"), ("icu", "2-1")]);
+        history.record(&mut newer, now, OFFICIAL);
+        assert!(newer.snoozed.is_empty());
+    }
+
+    #[test]
+    fn only_checked_sources_are_pruned() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let now = Local::now();
+        let mut history = PendingHistory::load(temp.path().join("pending.toml"));
+        history.snooze(
+            UpdateSource::Aur,
+            "foo-git",
+            "r2-1",
+            now + Duration::days(7),
+        );
+
+        // The official check finished first; the AUR list is not known yet.
+        history.record(&mut snapshot(&[("icu", "2-1")]), now, OFFICIAL);
+
+        let mut both = snapshot(&[("icu", "2-1")]);
+        both.aur.push(PackageUpdate::new(
+            "foo-git",
+            "r1-1",
+            "r2-1",
+            UpdateSource::Aur,
+        ));
+        history.record(&mut both, now, &[UpdateSource::Aur]);
+        assert_eq!(both.snoozed.len(), 1);
+        assert_eq!(both.snoozed[0].name, "foo-git");
+    }
+
+    #[test]
+    fn entries_without_a_source_apply_to_both_sources() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("pending.toml");
+        let now = Local::now();
+        fs::write(
+            &path,
+            format!(
+                "[[snoozed]]\nname = \"foo\"\nlatest = \"2-1\"\nuntil = \"{}\"\n",
+                (now + Duration::days(3)).to_rfc3339()
+            ),
+        )
+        .expect("write history");
+
+        let mut history = PendingHistory::load(path);
+        let mut current = snapshot(&[]);
+        current
+            .aur
+            .push(PackageUpdate::new("foo", "1-1", "2-1", UpdateSource::Aur));
+        history.record(&mut current, now, &[UpdateSource::Aur]);
+        assert_eq!(current.snoozed.len(), 1);
+    }
+}
@@ -1,114 +1,374 @@
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::Deserialize;
 
 use crate::state::Status;
 
-const CHECKING_XPM: &str = r#"/* XPM */
-static char * checking_xpm[] = {
-"16 16 2 1",
-"  c None",
-". c #f4b400",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................"
-};
-"#;
-
-const UP_TO_DATE_XPM: &str = r#"/* XPM */
-static char * uptodate_xpm[] = {
-"16 16 2 1",
-"  c None",
-". c #34a853",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................"
-};
-"#;
-
-const UPDATES_XPM: &str = r#"/* XPM */
-static char * updates_xpm[] = {
-"16 16 2 1",
-"  c None",
-". c #1a73e8",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................"
-};
-"#;
-
-const ERROR_XPM: &str = r#"/* XPM */
-static char * error_xpm[] = {
-"16 16 2 1",
-"  c None",
-". c #d93025",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................",
-"................"
-};
-"#;
+// Built-in 16x16 glyphs, drawn on a 16 unit grid so they stay crisp at panel sizes.
+const CHECKING_GLYPH: &str = r##"<g fill="none" stroke="#f4b400" stroke-width="2" stroke-linecap="round">
+    <path d="M13.5 8a5.5 5.5 0 0 1-9.6 3.7"/>
+    <path d="M2.5 8a5.5 5.5 0 0 1 9.6-3.7"/>
+  </g>
+  <path fill="#f4b400" d="M14 1.5v5h-5z"/>
+  <path fill="#f4b400" d="M2 14.5v-5h5z"/>"##;
+
+const UP_TO_DATE_GLYPH: &str = r##"<circle cx="8" cy="8" r="7.5" fill="#34a853"/>
+  <path d="M4.5 8.5l2.5 2.5 4.5-5" fill="none" stroke="#fff" stroke-width="2" stroke-linecap="round" stroke-linejoin="round"/>"##;
+
+const UPDATES_GLYPH: &str = r##"<circle cx="8" cy="8" r="7.5" fill="#1a73e8"/>
+  <path d="M8 3.5v8M4.5 8l3.5 3.5 3.5-3.5" fill="none" stroke="#fff" stroke-width="2" stroke-linecap="round" stroke-linejoin="round"/>"##;
+
+const OVERDUE_GLYPH: &str = r##"<path d="M8 1l7.5 13.5h-15z" fill="#f57c00" stroke="#f57c00" stroke-linejoin="round"/>
+  <path d="M8 5.5v4" stroke="#fff" stroke-width="2" stroke-linecap="round"/>
+  <circle cx="8" cy="12.25" r="1.1" fill="#fff"/>"##;
+
+const OFFLINE_GLYPH: &str = r##"<circle cx="8" cy="8" r="7.5" fill="#80868b"/>
+  <path d="M4 8h8" stroke="#fff" stroke-width="2" stroke-linecap="round"/>"##;
+
+const ERROR_GLYPH: &str = r##"<circle cx="8" cy="8" r="7.5" fill="#d93025"/>
+  <path d="M5.25 5.25l5.5 5.5M10.75 5.25l-5.5 5.5" stroke="#fff" stroke-width="2" stroke-linecap="round"/>"##;
+
+const ICON_SIZE: usize = 16;
+/// Frames of the spinning checking icon, each rotated by `360 / CHECKING_FRAMES` degrees.
+pub const CHECKING_FRAMES: usize = 8;
+const BADGE_PREFIX: &str = "pactrack-badge-";
+const CUSTOM_PREFIX: &str = "pactrack-custom-";
+/// Counts above this are shown as "99+".
+const BADGE_MAX_COUNT: usize = 99;
+
+/// 3x5 pixel digits for the count badge; `#` marks a lit pixel.
+const DIGIT_GLYPHS: [[&str; 5]; 10] = [
+    ["###", "#.#", "#.#", "#.#", "###"],
+    [".#.", "##.", ".#.", ".#.", "###"],
+    ["###", "..#", "###", "#..", "###"],
+    ["###", "..#", ".##", "..#", "###"],
+    ["#.#", "#.#", "###", "..#", "..#"],
+    ["###", "#..", "###", "..#", "###"],
+    ["###", "#..", "###", "#.#", "###"],
+    ["###", "..#", ".#.", ".#.", ".#."],
+    ["###", "#.#", "###", "#.#", "###"],
+    ["###", "#.#", "###", "..#", "###"],
+];
+const PLUS_GLYPH: [&str; 5] = ["...", ".#.", "###", ".#.", "..."];
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+/// `[icons]` entries: a theme icon name or an absolute SVG/PNG path per status.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct IconOverrides {
+    pub checking: Option<IconSpec>,
+    pub up_to_date: Option<IconSpec>,
+    pub updates_available: Option<IconSpec>,
+    pub overdue: Option<IconSpec>,
+    pub offline: Option<IconSpec>,
+    pub error: Option<IconSpec>,
+}
+
+impl IconOverrides {
+    pub fn get(&self, status: &Status) -> Option<&IconSpec> {
+        match status {
+            Status::Checking => self.checking.as_ref(),
+            Status::UpToDate => self.up_to_date.as_ref(),
+            Status::UpdatesAvailable => self.updates_available.as_ref(),
+            Status::Overdue => self.overdue.as_ref(),
+            Status::Offline => self.offline.as_ref(),
+            Status::Error => self.error.as_ref(),
+        }
+    }
+}
+
+/// `"name"` for every theme, or `{ light = "...", dark = "..." }`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum IconSpec {
+    Any(String),
+    Variants {
+        light: Option<String>,
+        dark: Option<String>,
+    },
+}
+
+impl IconSpec {
+    /// The icon for a dark or light theme; a missing variant falls back to the other one.
+    pub fn pick(&self, dark: bool) -> Option<&str> {
+        match self {
+            Self::Any(icon) => Some(icon),
+            Self::Variants {
+                light,
+                dark: dark_icon,
+            } => {
+                let (preferred, other) = if dark {
+                    (dark_icon, light)
+                } else {
+                    (light, dark_icon)
+                };
+                preferred.as_deref().or(other.as_deref())
+            }
+        }
+    }
+}
+
+fn status_key(status: &Status) -> &'static str {
+    match status {
+        Status::Checking => "checking",
+        Status::UpToDate => "up-to-date",
+        Status::UpdatesAvailable => "updates-available",
+        Status::Overdue => "overdue",
+        Status::Offline => "offline",
+        Status::Error => "error",
+    }
+}
+
+fn glyph(status: &Status) -> &'static str {
+    match status {
+        Status::Checking => CHECKING_GLYPH,
+        Status::UpToDate => UP_TO_DATE_GLYPH,
+        Status::UpdatesAvailable => UPDATES_GLYPH,
+        Status::Overdue => OVERDUE_GLYPH,
+        Status::Offline => OFFLINE_GLYPH,
+        Status::Error => ERROR_GLYPH,
+    }
+}
+
+fn svg(body: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{ICON_SIZE}\" height=\"{ICON_SIZE}\" \
+         viewBox=\"0 0 {ICON_SIZE} {ICON_SIZE}\">\n  {body}\n</svg>\n"
+    )
+}
+
+/// Badge background: red once updates are overdue, dark grey otherwise.
+fn badge_color(status: &Status) -> &'static str {
+    match status {
+        Status::Overdue => "#d93025",
+        _ => "#202124",
+    }
+}
+
+fn badge_text(count: usize) -> String {
+    if count > BADGE_MAX_COUNT {
+        format!("{BADGE_MAX_COUNT}+")
+    } else {
+        count.to_string()
+    }
+}
+
+/// The badge rectangle in the bottom-right corner and the lit pixels of the count inside it.
+#[derive(Debug, PartialEq, Eq)]
+struct BadgeLayout {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+    lit: Vec<(usize, usize)>,
+}
+
+fn badge_layout(count: usize) -> BadgeLayout {
+    let glyphs: Vec<&[&str; 5]> = badge_text(count)
+        .chars()
+        .map(|ch| match ch.to_digit(10) {
+            Some(digit) => &DIGIT_GLYPHS[digit as usize],
+            None => &PLUS_GLYPH,
+        })
+        .collect();
+
+    // One pixel of padding around the text and one pixel between glyphs.
+    let width = glyphs.len() * (GLYPH_WIDTH + 1) + 1;
+    let height = GLYPH_HEIGHT + 2;
+    let left = ICON_SIZE - width;
+    let top = ICON_SIZE - height;
+
+    let mut lit = Vec::new();
+    for (index, glyph) in glyphs.iter().enumerate() {
+        let x = left + 1 + index * (GLYPH_WIDTH + 1);
+        for (dy, line) in glyph.iter().enumerate() {
+            for (dx, pixel) in line.chars().enumerate() {
+                if pixel == '#' {
+                    lit.push((x + dx, top + 1 + dy));
+                }
+            }
+        }
+    }
+    BadgeLayout {
+        left,
+        top,
+        width,
+        height,
+        lit,
+    }
+}
+
+/// Renders the built-in icon for `status` with `count` drawn in a badge in the
+/// bottom-right corner.
+pub fn render_badge_svg(status: &Status, count: usize) -> String {
+    let BadgeLayout {
+        left,
+        top,
+        width,
+        height,
+        lit,
+    } = badge_layout(count);
+    let mut body = format!(
+        "{}\n  <rect x=\"{left}\" y=\"{top}\" width=\"{width}\" height=\"{height}\" rx=\"1.5\" \
+         fill=\"{}\"/>\n  <g fill=\"#fff\">",
+        glyph(status),
+        badge_color(status)
+    );
+    for (x, y) in lit {
+        body.push_str(&format!(
+            "<rect x=\"{x}\" y=\"{y}\" width=\"1\" height=\"1\"/>"
+        ));
+    }
+    body.push_str("</g>");
+    svg(&body)
+}
+
+/// Removes files starting with `prefix` from `dir`, except `keep`.
+fn remove_stale(dir: &Path, prefix: &str, keep: &str) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let stale = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|existing| existing.starts_with(prefix) && existing != keep);
+        if stale {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the badge icon for `status` and `count` into `dir` and returns its icon name,
+/// or `None` when the status shows no count. Badge icons for other counts are removed, so
+/// the directory holds at most one.
+///
+/// Each count gets its own icon name because indicator hosts cache icons by name.
+pub fn install_badge_icon(dir: &Path, status: &Status, count: usize) -> io::Result<Option<String>> {
+    if count == 0 || !matches!(status, Status::UpdatesAvailable | Status::Overdue) {
+        return Ok(None);
+    }
+
+    let severity = match status {
+        Status::Overdue => "overdue",
+        _ => "updates",
+    };
+    let name = format!(
+        "{BADGE_PREFIX}{severity}-{}",
+        badge_text(count).replace('+', "plus")
+    );
+    let file_name = format!("{name}.svg");
+    remove_stale(dir, BADGE_PREFIX, &file_name)?;
+
+    let path = dir.join(&file_name);
+    if !path.exists() {
+        fs::write(&path, render_badge_svg(status, count))?;
+    }
+    Ok(Some(name))
+}
+
+/// The icon configured in `[icons]` for `status`, or `None` to use the defaults.
+///
+/// Theme names must exist in the current icon theme (`has_theme_icon`). Absolute paths are
+/// copied into `dir` under a pactrack name, since not every tray host loads icons from
+/// arbitrary paths but all of them search the indicator's icon theme path.
+pub fn custom_icon(
+    overrides: &IconOverrides,
+    status: &Status,
+    dark: bool,
+    dir: &Path,
+    has_theme_icon: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let icon = overrides.get(status)?.pick(dark)?;
+    let path = Path::new(icon);
+    if !path.is_absolute() {
+        if has_theme_icon(icon) {
+            return Some(icon.to_string());
+        }
+        warn!("icon theme has no icon named {icon}; using the default");
+        return None;
+    }
+
+    match install_custom_icon(dir, status, dark, path) {
+        Ok(name) => Some(name),
+        Err(err) => {
+            warn!("cannot use icon {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+fn install_custom_icon(dir: &Path, status: &Status, dark: bool, path: &Path) -> io::Result<String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .filter(|ext| matches!(ext.as_str(), "svg" | "png"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an SVG or PNG file"))?;
+
+    let variant = if dark { "dark" } else { "light" };
+    let name = format!("{CUSTOM_PREFIX}{}-{variant}", status_key(status));
+    let target = dir.join(format!("{name}.{extension}"));
+
+    // Another extension under the same name would shadow the copy in icon lookups.
+    for other in ["svg", "png"] {
+        if other != extension {
+            let _ = fs::remove_file(dir.join(format!("{name}.{other}")));
+        }
+    }
+
+    let source_modified = fs::metadata(path)?.modified()?;
+    let up_to_date = fs::metadata(&target)
+        .and_then(|meta| meta.modified())
+        .is_ok_and(|copied| copied >= source_modified);
+    if !up_to_date {
+        fs::copy(path, &target)?;
+    }
+    Ok(name)
+}
+
+/// Icon name of one frame of the checking animation.
+pub fn checking_frame_name(frame: usize) -> String {
+    format!("pactrack-checking-{}", frame % CHECKING_FRAMES)
+}
+
+fn render_checking_frame(frame: usize) -> String {
+    let degrees = 360 * (frame % CHECKING_FRAMES) / CHECKING_FRAMES;
+    svg(&format!(
+        "<g transform=\"rotate({degrees} 8 8)\">\n  {CHECKING_GLYPH}\n  </g>"
+    ))
+}
 
 pub fn install_fallback_icons() -> io::Result<PathBuf> {
     let base = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
     let dir = base.join("pactrack").join("icons");
     fs::create_dir_all(&dir)?;
 
-    fs::write(dir.join("pactrack-checking.xpm"), CHECKING_XPM)?;
-    fs::write(dir.join("pactrack-up-to-date.xpm"), UP_TO_DATE_XPM)?;
-    fs::write(dir.join("pactrack-updates-available.xpm"), UPDATES_XPM)?;
-    fs::write(dir.join("pactrack-error.xpm"), ERROR_XPM)?;
+    // Earlier versions wrote XPM squares under the same names.
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "xpm") {
+            fs::remove_file(path)?;
+        }
+    }
+
+    for status in [
+        Status::Checking,
+        Status::UpToDate,
+        Status::UpdatesAvailable,
+        Status::Overdue,
+        Status::Offline,
+        Status::Error,
+    ] {
+        let file = format!("pactrack-{}.svg", status_key(&status));
+        fs::write(dir.join(file), svg(glyph(&status)))?;
+    }
+    for frame in 0..CHECKING_FRAMES {
+        let file = format!("{}.svg", checking_frame_name(frame));
+        fs::write(dir.join(file), render_checking_frame(frame))?;
+    }
 
     Ok(dir)
 }
@@ -118,6 +378,158 @@ pub fn icon_candidates(status: &Status) -> (&'static str, &'static str) {
         Status::Checking => ("view-refresh-symbolic", "pactrack-checking"),
         Status::UpToDate => ("emblem-default", "pactrack-up-to-date"),
         Status::UpdatesAvailable => ("software-update-available", "pactrack-updates-available"),
+        Status::Overdue => ("dialog-warning", "pactrack-overdue"),
+        Status::Offline => ("network-offline", "pactrack-offline"),
         Status::Error => ("dialog-error", "pactrack-error"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge_draws_the_count_in_the_corner() {
+        let layout = badge_layout(7);
+        assert_eq!(
+            (layout.left, layout.top, layout.width, layout.height),
+            (11, 9, 5, 7)
+        );
+        assert_eq!(
+            layout.lit,
+            [
+                (12, 10),
+                (13, 10),
+                (14, 10),
+                (14, 11),
+                (13, 12),
+                (13, 13),
+                (13, 14)
+            ]
+        );
+
+        let svg = render_badge_svg(&Status::UpdatesAvailable, 7);
+        assert!(svg.contains(UPDATES_GLYPH));
+        assert!(svg.contains("<rect x=\"11\" y=\"9\" width=\"5\" height=\"7\""));
+        assert!(svg.contains("<rect x=\"12\" y=\"10\" width=\"1\" height=\"1\"/>"));
+    }
+
+    #[test]
+    fn large_counts_are_capped() {
+        let layout = badge_layout(1234);
+        assert_eq!((layout.left, layout.width), (3, 13));
+        // The middle row of "9", "9" and "+" is fully lit.
+        let middle: Vec<usize> = layout
+            .lit
+            .iter()
+            .filter(|(_, y)| *y == 12)
+            .map(|(x, _)| *x)
+            .collect();
+        assert_eq!(middle, [4, 5, 6, 8, 9, 10, 12, 13, 14]);
+        assert!(render_badge_svg(&Status::Overdue, 1234).contains("fill=\"#d93025\""));
+    }
+
+    #[test]
+    fn installing_a_badge_replaces_the_previous_one() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        fs::write(temp.path().join("pactrack-error.svg"), svg(ERROR_GLYPH)).expect("write");
+
+        let first = install_badge_icon(temp.path(), &Status::UpdatesAvailable, 3).expect("install");
+        assert_eq!(first.as_deref(), Some("pactrack-badge-updates-3"));
+        let second = install_badge_icon(temp.path(), &Status::Overdue, 120).expect("install");
+        assert_eq!(second.as_deref(), Some("pactrack-badge-overdue-99plus"));
+
+        let mut files: Vec<String> = fs::read_dir(temp.path())
+            .expect("read dir")
+            .map(|entry| {
+                entry
+                    .expect("entry")
+                    .file_name()
+                    .into_string()
+                    .expect("utf-8")
+            })
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            ["pactrack-badge-overdue-99plus.svg", "pactrack-error.svg"]
+        );
+
+        let none = install_badge_icon(temp.path(), &Status::UpToDate, 0).expect("install");
+        assert_eq!(none, None);
+    }
+
+    #[test]
+    fn checking_frames_rotate_the_glyph() {
+        assert_eq!(checking_frame_name(3), "pactrack-checking-3");
+        assert_eq!(checking_frame_name(CHECKING_FRAMES), "pactrack-checking-0");
+
+        assert!(render_checking_frame(0).contains("rotate(0 8 8)"));
+        let frame = render_checking_frame(3);
+        assert!(frame.contains("rotate(135 8 8)"));
+        assert!(frame.contains(CHECKING_GLYPH));
+    }
+
+    #[test]
+    fn icon_variants_fall_back_to_each_other() {
+        let both = IconSpec::Variants {
+            light: Some("light-glyph".to_string()),
+            dark: Some("dark-glyph".to_string()),
+        };
+        assert_eq!(both.pick(false), Some("light-glyph"));
+        assert_eq!(both.pick(true), Some("dark-glyph"));
+
+        let light_only = IconSpec::Variants {
+            light: Some("light-glyph".to_string()),
+            dark: None,
+        };
+        assert_eq!(light_only.pick(true), Some("light-glyph"));
+        let dark_only = IconSpec::Variants {
+            light: None,
+            dark: Some("dark-glyph".to_string()),
+        };
+        assert_eq!(dark_only.pick(false), Some("dark-glyph"));
+        assert_eq!(IconSpec::Any("icon".to_string()).pick(true), Some("icon"));
+    }
+
+    #[test]
+    fn custom_icons_resolve_theme_names_and_copy_files() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let icon_dir = temp.path().join("icons");
+        fs::create_dir(&icon_dir).expect("mkdir");
+        let source = temp.path().join("My Error.SVG");
+        fs::write(&source, svg(ERROR_GLYPH)).expect("write");
+
+        let overrides = IconOverrides {
+            up_to_date: Some(IconSpec::Any("checkbox-checked".to_string())),
+            offline: Some(IconSpec::Any("no-such-icon".to_string())),
+            error: Some(IconSpec::Variants {
+                light: None,
+                dark: Some(source.display().to_string()),
+            }),
+            ..IconOverrides::default()
+        };
+        let theme = |name: &str| name == "checkbox-checked";
+
+        assert_eq!(
+            custom_icon(&overrides, &Status::UpToDate, false, &icon_dir, theme).as_deref(),
+            Some("checkbox-checked")
+        );
+        assert_eq!(
+            custom_icon(&overrides, &Status::Offline, false, &icon_dir, theme),
+            None
+        );
+        assert_eq!(
+            custom_icon(&overrides, &Status::Checking, false, &icon_dir, theme),
+            None
+        );
+        assert_eq!(
+            custom_icon(&overrides, &Status::Error, true, &icon_dir, theme).as_deref(),
+            Some("pactrack-custom-error-dark")
+        );
+        assert_eq!(
+            fs::read_to_string(icon_dir.join("pactrack-custom-error-dark.svg")).expect("copy"),
+            svg(ERROR_GLYPH)
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::state::{InstallReason, PackageUpdate};
+
+/// Read-only view over pacman's local package database (`<DBPath>/local`).
+#[derive(Clone, Debug)]
+pub struct LocalDb {
+    local_dir: PathBuf,
+}
+
+impl LocalDb {
+    pub fn new(db_path: &Path) -> Self {
+        Self {
+            local_dir: db_path.join("local"),
+        }
+    }
+
+    pub fn package_desc(&self, name: &str, version: &str) -> Option<PackageDesc> {
+        let path = self
+            .local_dir
+            .join(format!("{name}-{version}"))
+            .join("desc");
+        let raw = fs::read_to_string(path).ok()?;
+        Some(PackageDesc::parse(&raw))
+    }
+
+    pub fn is_installed(&self, name: &str, version: &str) -> bool {
+        self.local_dir.join(format!("{name}-{version}")).is_dir()
+    }
+
+    /// Updates whose installed version is still the one they were reported against.
+    pub fn still_pending(&self, updates: &[PackageUpdate]) -> Vec<PackageUpdate> {
+        updates
+            .iter()
+            .filter(|update| self.is_installed(&update.name, &update.current))
+            .cloned()
+            .collect()
+    }
+
+    pub fn install_reason(&self, name: &str, version: &str) -> Option<InstallReason> {
+        self.package_desc(name, version).map(|desc| desc.reason())
+    }
+
+    pub fn installed_packages(&self) -> Vec<InstalledPackage> {
+        let Ok(entries) = fs::read_dir(&self.local_dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| fs::read_to_string(entry.path().join("desc")).ok())
+            .filter_map(|raw| InstalledPackage::from_desc(&PackageDesc::parse(&raw)))
+            .collect()
+    }
+
+    pub fn annotate_reasons(&self, updates: &mut [PackageUpdate]) {
+        for update in updates {
+            if let Some(reason) = self.install_reason(&update.name, &update.current) {
+                update.reason = reason;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub depends: Vec<String>,
+    pub provides: Vec<String>,
+}
+
+impl InstalledPackage {
+    fn from_desc(desc: &PackageDesc) -> Option<Self> {
+        Some(Self {
+            name: desc.first("NAME")?.to_string(),
+            version: desc.first("VERSION").unwrap_or_default().to_string(),
+            depends: strip_all(desc.values("DEPENDS")),
+            provides: strip_all(desc.values("PROVIDES")),
+        })
+    }
+
+    pub fn sonames(&self) -> impl Iterator<Item = &str> {
+        self.provides
+            .iter()
+            .map(String::as_str)
+            .filter(|provide| is_soname(provide))
+    }
+}
+
+/// Drops the version constraint from a dependency or provision (`libssl.so=3-64` -> `libssl.so`).
+pub fn strip_version_constraint(entry: &str) -> &str {
+    entry
+        .split(['<', '>', '=', ':'])
+        .next()
+        .unwrap_or(entry)
+        .trim()
+}
+
+pub fn is_soname(entry: &str) -> bool {
+    entry.ends_with(".so") || entry.contains(".so.")
+}
+
+fn strip_all(values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .map(|value| strip_version_constraint(value).to_string())
+        .collect()
+}
+
+/// Parsed `desc` file: `%SECTION%` headers followed by one value per line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackageDesc {
+    sections: HashMap<String, Vec<String>>,
+}
+
+impl PackageDesc {
+    pub fn parse(raw: &str) -> Self {
+        let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for line in raw.lines().map(str::trim) {
+            if line.is_empty() {
+                current = None;
+                continue;
+            }
+
+            if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+                let key = line.trim_matches('%').to_string();
+                sections.entry(key.clone()).or_default();
+                current = Some(key);
+                continue;
+            }
+
+            if let Some(key) = &current {
+                sections
+                    .entry(key.clone())
+                    .or_default()
+                    .push(line.to_string());
+            }
+        }
+
+        Self { sections }
+    }
+
+    pub fn values(&self, key: &str) -> &[String] {
+        self.sections.get(key).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn first(&self, key: &str) -> Option<&str> {
+        self.values(key).first().map(String::as_str)
+    }
+
+    pub fn reason(&self) -> InstallReason {
+        match self.first("REASON") {
+            Some("1") => InstallReason::Dependency,
+            _ => InstallReason::Explicit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::UpdateSource;
+
+    fn write_desc(root: &Path, dir: &str, body: &str) {
+        let pkg_dir = root.join("local").join(dir);
+        fs::create_dir_all(&pkg_dir).expect("create pkg dir");
+        fs::write(pkg_dir.join("desc"), body).expect("write desc");
+    }
+
+    #[test]
+    fn parses_desc_sections() {
+        let desc = PackageDesc::parse(
+            "%NAME%\nopenssl\n\n%VERSION%\n3.1.5-1\n\n%PROVIDES%\nlibcrypto.so=3-64\nlibssl.so=3-64\n",
+        );
+
+        assert_eq!(desc.first("NAME"), Some("openssl"));
+        assert_eq!(desc.values("PROVIDES").len(), 2);
+        assert!(desc.values("DEPENDS").is_empty());
+        assert_eq!(desc.reason(), InstallReason::Explicit);
+    }
+
+    #[test]
+    fn lists_installed_packages_with_stripped_constraints() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        write_desc(
+            temp.path(),
+            "openssl-3.1.5-1",
+            "%NAME%\nopenssl\n\n%VERSION%\n3.1.5-1\n\n%DEPENDS%\nglibc>=2.38\n\n%PROVIDES%\nlibssl.so=3-64\n",
+        );
+
+        let installed = LocalDb::new(temp.path()).installed_packages();
+        assert_eq!(installed.len(), 1);
+        assert_eq!(installed[0].depends, vec!["glibc".to_string()]);
+        assert_eq!(
+            installed[0].sonames().collect::<Vec<_>>(),
+            vec!["libssl.so"]
+        );
+    }
+
+    #[test]
+    fn annotates_dependency_reason_from_local_db() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        write_desc(temp.path(), "icu-74.1-1", "%NAME%\nicu\n\n%REASON%\n1\n");
+        write_desc(temp.path(), "firefox-121.0-1", "%NAME%\nfirefox\n");
+
+        let mut updates = vec![
+            PackageUpdate::new("icu", "74.1-1", "74.2-1", UpdateSource::Official),
+            PackageUpdate::new("firefox", "121.0-1", "122.0-1", UpdateSource::Official),
+            PackageUpdate::new("missing", "1-1", "2-1", UpdateSource::Official),
+        ];
+        LocalDb::new(temp.path()).annotate_reasons(&mut updates);
+
+        assert_eq!(updates[0].reason, InstallReason::Dependency);
+        assert_eq!(updates[1].reason, InstallReason::Explicit);
+        assert_eq!(updates[2].reason, InstallReason::Explicit);
+    }
+
+    #[test]
+    fn keeps_only_updates_whose_installed_version_is_unchanged() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        write_desc(temp.path(), "foo-git-r1-1", "%NAME%\nfoo-git\n");
+        write_desc(temp.path(), "bar-2-1", "%NAME%\nbar\n");
+
+        let updates = vec![
+            PackageUpdate::new("foo-git", "r1-1", "r2-1", UpdateSource::Aur),
+            PackageUpdate::new("bar", "1-1", "2-1", UpdateSource::Aur),
+        ];
+        let pending = LocalDb::new(temp.path()).still_pending(&updates);
+
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].name, "foo-git");
+    }
+}
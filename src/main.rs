@@ -1,19 +1,44 @@
+mod bus;
+mod cache;
+mod checklist;
 mod commands;
 mod config;
+mod details;
+mod diff;
+mod headless;
+mod history;
 mod icons;
+mod localdb;
+mod network;
 mod notifier;
 mod parser;
+mod power;
+mod preferences;
+mod rebuilds;
+mod report;
+mod resume;
+mod retry;
+mod schedule;
 mod scheduler;
+mod settings;
+mod sni;
 mod state;
 mod tray;
+mod upgrade;
+mod watcher;
 
 use std::path::PathBuf;
 
+use chrono::Local;
 use clap::Parser;
-use log::{error, info};
+use log::{error, info, warn};
 
-use crate::commands::perform_check;
-use crate::config::{CliOverrides, load_config};
+use crate::cache::{default_cache_path, load_cached_check};
+use crate::commands::{DetectedAurHelper, perform_check};
+use crate::config::{CliOverrides, ConfigSource, load_config};
+use crate::history::{PendingHistory, default_history_path};
+use crate::report::render_details;
+use crate::state::{UpdateSnapshot, UpdateSource};
 
 #[derive(Debug, Parser)]
 #[command(name = "pactrack", version, about = "Arch package update tray tracker")]
@@ -29,6 +54,18 @@ struct Cli {
 
     #[arg(long)]
     once: bool,
+
+    /// With --once: print the last cached check without touching the network.
+    #[arg(long, requires = "once")]
+    cached: bool,
+
+    /// Run without a tray icon, printing status changes and sending notifications.
+    #[arg(long, conflicts_with = "once")]
+    headless: bool,
+
+    /// Run headless when the tray cannot start, instead of exiting with an error.
+    #[arg(long, conflicts_with_all = ["once", "headless"])]
+    headless_fallback: bool,
 }
 
 fn main() {
@@ -50,26 +87,94 @@ fn main() {
 
     info!("using config path: {}", config_path.display());
 
+    if cli.once && cli.cached {
+        let cache_path = default_cache_path();
+        match load_cached_check(&cache_path) {
+            Ok(Some(cached)) => {
+                let checked = cached
+                    .state
+                    .last_checked
+                    .map(|ts| ts.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                println!("cached check from: {checked}");
+                print_check(&cached.snapshot, cached.helper);
+            }
+            Ok(None) => {
+                error!("no cached check at {}", cache_path.display());
+                std::process::exit(1);
+            }
+            Err(err) => {
+                error!(
+                    "failed to read cached check at {}: {err}",
+                    cache_path.display()
+                );
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     if cli.once {
         match perform_check(&config) {
-            Ok(result) => {
-                println!("official updates: {}", result.snapshot.official.len());
-                println!("aur updates: {}", result.snapshot.aur.len());
-                println!("total updates: {}", result.snapshot.total_count());
-                if let Some(helper) = result.helper {
-                    println!("detected aur helper: {helper}");
+            Ok(mut result) => {
+                let mut history = PendingHistory::load(default_history_path());
+                // A failed source's list is empty, not current; leave its history alone.
+                let checked: Vec<UpdateSource> = [UpdateSource::Official, UpdateSource::Aur]
+                    .into_iter()
+                    .filter(|source| result.failures.iter().all(|(failed, _)| failed != source))
+                    .collect();
+                history.record(&mut result.snapshot, Local::now(), &checked);
+                if let Err(err) = history.save() {
+                    warn!("failed to save pending history: {err}");
+                }
+                for (source, err) in &result.failures {
+                    println!("{} check failed: {err}", source.label().to_lowercase());
+                }
+                print_check(&result.snapshot, result.helper);
+                if !result.failures.is_empty() {
+                    std::process::exit(1);
                 }
             }
-            Err(err) => {
-                error!("one-shot check failed: {err}");
+            Err(failures) => {
+                for (source, err) in &failures {
+                    error!("one-shot {} check failed: {err}", source.label());
+                }
                 std::process::exit(1);
             }
         }
         return;
     }
 
-    if let Err(err) = tray::run(config) {
-        error!("failed to start tray app: {err}");
+    let config_source = ConfigSource {
+        path: config_path,
+        overrides,
+    };
+    if !cli.headless {
+        match tray::run(config.clone(), config_source.clone()) {
+            Ok(()) => return,
+            Err(err) if cli.headless_fallback => {
+                warn!("failed to start tray app ({err}); running headless")
+            }
+            // Exiting non-zero lets a supervisor such as systemd retry once the tray host is up.
+            Err(err) => {
+                error!("failed to start tray app: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Err(err) = headless::run(config, config_source) {
+        error!("{err}");
         std::process::exit(1);
     }
 }
+
+fn print_check(snapshot: &UpdateSnapshot, helper: Option<DetectedAurHelper>) {
+    println!("official updates: {}", snapshot.official.len());
+    println!("aur updates: {}", snapshot.aur.len());
+    println!("total updates: {}", snapshot.total_count());
+    if let Some(helper) = helper {
+        println!("detected aur helper: {helper}");
+    }
+    println!();
+    println!("{}", render_details(snapshot, Local::now()));
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bus::SystemBus;
+
+const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const NM_INTERFACE: &str = "org.freedesktop.NetworkManager";
+
+/// NMMetered values that mean "metered": `NM_METERED_YES` and `NM_METERED_GUESS_YES`.
+const NM_METERED_VALUES: [u32; 2] = [1, 3];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    Online,
+    Metered,
+    Offline,
+}
+
+/// Decides whether checks can reach the network: default-route presence from procfs, plus
+/// NetworkManager's metered flag over D-Bus when asked for and available.
+pub struct ConnectivityProbe {
+    proc_root: PathBuf,
+    check_route: bool,
+    check_metered: bool,
+    system_bus: SystemBus,
+}
+
+impl ConnectivityProbe {
+    pub fn new(proc_root: PathBuf, check_route: bool, check_metered: bool) -> Self {
+        Self {
+            proc_root,
+            check_route,
+            check_metered,
+            system_bus: SystemBus::default(),
+        }
+    }
+
+    pub fn probe(&mut self) -> Connectivity {
+        if self.check_route && !has_default_route(&self.proc_root) {
+            return Connectivity::Offline;
+        }
+
+        if self.check_metered && self.is_metered() {
+            return Connectivity::Metered;
+        }
+
+        Connectivity::Online
+    }
+
+    fn is_metered(&mut self) -> bool {
+        self.system_bus
+            .property::<u32>(NM_SERVICE, NM_PATH, NM_INTERFACE, "Metered")
+            .is_some_and(|value| NM_METERED_VALUES.contains(&value))
+    }
+}
+
+/// True when the routing table under `proc_root` (normally `/proc`) has an IPv4 or IPv6
+/// default route that is up and not bound to the loopback interface.
+pub fn has_default_route(proc_root: &Path) -> bool {
+    let net = proc_root.join("net");
+    let ipv4 = fs::read_to_string(net.join("route"))
+        .map(|raw| ipv4_has_default_route(&raw))
+        .unwrap_or(false);
+
+    ipv4 || fs::read_to_string(net.join("ipv6_route"))
+        .map(|raw| ipv6_has_default_route(&raw))
+        .unwrap_or(false)
+}
+
+/// `/proc/net/route`: `Iface Destination Gateway Flags RefCnt Use Metric Mask ...`.
+fn ipv4_has_default_route(raw: &str) -> bool {
+    const RTF_UP: u32 = 0x1;
+
+    raw.lines().skip(1).any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [iface, destination, _gateway, flags, _, _, _, mask, ..] = fields.as_slice() else {
+            return false;
+        };
+        let flags = u32::from_str_radix(flags, 16).unwrap_or(0);
+
+        *iface != "lo" && *destination == "00000000" && *mask == "00000000" && flags & RTF_UP != 0
+    })
+}
+
+/// `/proc/net/ipv6_route`: `dest dest_len src src_len next_hop metric refcnt use flags iface`.
+fn ipv6_has_default_route(raw: &str) -> bool {
+    const RTF_UP: u32 = 0x1;
+    const RTF_REJECT: u32 = 0x200;
+
+    raw.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [destination, prefix_len, .., flags, iface] = fields.as_slice() else {
+            return false;
+        };
+        let flags = u32::from_str_radix(flags, 16).unwrap_or(0);
+
+        *iface != "lo"
+            && destination.chars().all(|c| c == '0')
+            && *prefix_len == "00"
+            && flags & RTF_UP != 0
+            && flags & RTF_REJECT == 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTE_HEADER: &str =
+        "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n";
+
+    fn write_proc(root: &Path, route: &str, ipv6_route: &str) {
+        let net = root.join("net");
+        fs::create_dir_all(&net).expect("create net dir");
+        fs::write(net.join("route"), route).expect("write route");
+        fs::write(net.join("ipv6_route"), ipv6_route).expect("write ipv6_route");
+    }
+
+    #[test]
+    fn detects_ipv4_default_route() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let route = format!(
+            "{ROUTE_HEADER}wlan0\t00000000\t0100A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\nwlan0\t0000A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\t0\t0\t0\n"
+        );
+        write_proc(temp.path(), &route, "");
+
+        assert!(has_default_route(temp.path()));
+    }
+
+    #[test]
+    fn link_local_routes_only_means_offline() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let route = format!(
+            "{ROUTE_HEADER}wlan0\t0000A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\t0\t0\t0\n"
+        );
+        let ipv6 = "fe800000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001 wlan0\n\
+                    00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200 lo\n";
+        write_proc(temp.path(), &route, ipv6);
+
+        assert!(!has_default_route(temp.path()));
+    }
+
+    #[test]
+    fn detects_ipv6_default_route() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let ipv6 = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe80000000000000022186fffe0a1b2c 00000400 00000001 00000000 00450003 eth0\n";
+        write_proc(temp.path(), ROUTE_HEADER, ipv6);
+
+        assert!(has_default_route(temp.path()));
+    }
+
+    #[test]
+    fn missing_procfs_means_offline() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let mut probe = ConnectivityProbe::new(temp.path().to_path_buf(), true, false);
+        assert_eq!(probe.probe(), Connectivity::Offline);
+
+        let mut disabled = ConnectivityProbe::new(temp.path().to_path_buf(), false, false);
+        assert_eq!(disabled.probe(), Connectivity::Online);
+    }
+}
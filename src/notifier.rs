@@ -1,14 +1,63 @@
+use chrono::{DateTime, Local};
 use log::debug;
 
+use crate::config::EffectiveConfig;
+use crate::diff::SnapshotDiff;
+use crate::report::{plural, summarize_names};
+use crate::schedule::is_quiet;
+use crate::state::{PackageUpdate, UpdateSnapshot, UpdateSource};
+
 const SUMMARY: &str = "Pactrack";
 const ICON: &str = "software-update-available";
+const NAME_LIMIT: usize = 5;
+
+/// Remembers the last snapshot the user was told about, so changes that arrive during quiet
+/// hours are announced together once the quiet window ends.
+#[derive(Debug, Default)]
+pub struct Announcer {
+    announced: Option<UpdateSnapshot>,
+    latest: Option<UpdateSnapshot>,
+}
 
-pub fn notify_count_change(previous: usize, current: usize) {
-    let body = notification_body(previous, current);
+impl Announcer {
+    pub fn observe(&mut self, snapshot: &UpdateSnapshot) {
+        self.latest = Some(snapshot.clone());
+    }
 
+    /// The notification for what changed since the last announced snapshot, or `None` while
+    /// quiet hours are active or nothing new was observed. The first snapshot only sets the
+    /// baseline. Updates the user snoozed are not reported as no longer pending.
+    pub fn take_message(
+        &mut self,
+        config: &EffectiveConfig,
+        now: DateTime<Local>,
+    ) -> Option<String> {
+        if is_quiet(&config.quiet_hours, now) {
+            return None;
+        }
+        let latest = self.latest.take()?;
+        let message = self
+            .announced
+            .as_ref()
+            .filter(|_| config.notify_on_change)
+            .and_then(|announced| {
+                let mut diff = SnapshotDiff::between(announced, &latest);
+                diff.removed.retain(|removed| {
+                    !latest.snoozed.iter().any(|snoozed| {
+                        snoozed.source == removed.source && snoozed.name == removed.name
+                    })
+                });
+                notification_body(&diff, &latest, config.notify_on_decrease)
+            });
+        self.announced = Some(latest);
+        message
+    }
+}
+
+pub fn notify(body: &str) {
     let result = notify_rust::Notification::new()
         .summary(SUMMARY)
-        .body(&body)
+        .body(body)
         .icon(ICON)
         .show();
 
@@ -17,30 +66,277 @@ pub fn notify_count_change(previous: usize, current: usize) {
     }
 }
 
-fn notification_body(previous: usize, current: usize) -> String {
-    format!("Pending updates changed from {} to {}", previous, current)
+/// Builds the notification text for a snapshot change, or `None` when nothing worth
+/// notifying about happened.
+fn notification_body(
+    diff: &SnapshotDiff,
+    current: &UpdateSnapshot,
+    notify_on_decrease: bool,
+) -> Option<String> {
+    if diff.is_empty() {
+        return None;
+    }
+
+    let total = current.total_count();
+    if !diff.has_arrivals() {
+        if !notify_on_decrease {
+            return None;
+        }
+        let removed = diff.removed.len();
+        return Some(format!(
+            "{removed} {} no longer pending ({total} remaining)",
+            plural(removed, "update", "updates")
+        ));
+    }
+
+    let mut parts = Vec::new();
+    if !diff.added.is_empty() {
+        parts.push(format!(
+            "{} new {}",
+            diff.added.len(),
+            plural(diff.added.len(), "update", "updates")
+        ));
+    }
+    if !diff.bumped.is_empty() {
+        parts.push(format!("{} updated again", diff.bumped.len()));
+    }
+    let header = format!("{} ({total} pending)", parts.join(", "));
+
+    let mut lines = vec![header];
+    for (source, label) in [
+        (UpdateSource::Official, "Official"),
+        (UpdateSource::Aur, "AUR"),
+    ] {
+        let arrivals: Vec<&PackageUpdate> = diff
+            .arrivals()
+            .filter(|update| update.source == source)
+            .collect();
+        if let Some(line) = source_line(label, &arrivals) {
+            lines.push(line);
+        }
+    }
+
+    Some(lines.join("\n"))
+}
+
+/// One line per source: explicitly installed packages with versions, dependencies collapsed.
+fn source_line(label: &str, arrivals: &[&PackageUpdate]) -> Option<String> {
+    if arrivals.is_empty() {
+        return None;
+    }
+
+    let explicit: Vec<String> = arrivals
+        .iter()
+        .filter(|update| update.is_explicit())
+        .map(|update| format!("{} {}", update.name, update.latest))
+        .collect();
+    let dependencies = arrivals.len() - explicit.len();
+
+    let mut parts = Vec::new();
+    if !explicit.is_empty() {
+        let names: Vec<&str> = explicit.iter().map(String::as_str).collect();
+        parts.push(summarize_names(&names, NAME_LIMIT));
+    }
+    if dependencies > 0 {
+        parts.push(format!(
+            "+ {dependencies} {}",
+            plural(dependencies, "dependency", "dependencies")
+        ));
+    }
+
+    Some(format!("{label}: {}", parts.join(" ")))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{notification_body, notify_count_change};
+    use chrono::{Local, NaiveTime, TimeZone};
+
+    use super::{Announcer, notification_body};
+    use crate::config::EffectiveConfig;
+    use crate::diff::SnapshotDiff;
+    use crate::state::{InstallReason, PackageUpdate, UpdateSnapshot, UpdateSource};
+
+    fn update(
+        name: &str,
+        latest: &str,
+        source: UpdateSource,
+        reason: InstallReason,
+    ) -> PackageUpdate {
+        let mut update = PackageUpdate::new(name, "1-1", latest, source);
+        update.reason = reason;
+        update
+    }
+
+    fn previous() -> UpdateSnapshot {
+        UpdateSnapshot {
+            official: vec![
+                update(
+                    "firefox",
+                    "2-1",
+                    UpdateSource::Official,
+                    InstallReason::Explicit,
+                ),
+                update(
+                    "icu",
+                    "2-1",
+                    UpdateSource::Official,
+                    InstallReason::Dependency,
+                ),
+            ],
+            ..UpdateSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn notification_body_lists_new_updates_by_source() {
+        let current = UpdateSnapshot {
+            official: vec![
+                update(
+                    "firefox",
+                    "3-1",
+                    UpdateSource::Official,
+                    InstallReason::Explicit,
+                ),
+                update(
+                    "icu",
+                    "2-1",
+                    UpdateSource::Official,
+                    InstallReason::Dependency,
+                ),
+                update(
+                    "neovim",
+                    "2-1",
+                    UpdateSource::Official,
+                    InstallReason::Explicit,
+                ),
+                update(
+                    "zstd",
+                    "2-1",
+                    UpdateSource::Official,
+                    InstallReason::Dependency,
+                ),
+            ],
+            aur: vec![update(
+                "foo-git",
+                "r2-1",
+                UpdateSource::Aur,
+                InstallReason::Explicit,
+            )],
+            ..UpdateSnapshot::default()
+        };
+
+        let diff = SnapshotDiff::between(&previous(), &current);
+        let body = notification_body(&diff, &current, false);
+        assert_eq!(
+            body.as_deref(),
+            Some(
+                "3 new updates, 1 updated again (5 pending)\nOfficial: neovim 2-1, firefox 3-1 + 1 dependency\nAUR: foo-git r2-1"
+            )
+        );
+    }
 
     #[test]
-    fn notification_body_formats_counts() {
-        let body = notification_body(2, 5);
-        assert_eq!(body, "Pending updates changed from 2 to 5");
+    fn decreases_only_notify_when_enabled() {
+        let current = UpdateSnapshot {
+            official: vec![update(
+                "firefox",
+                "2-1",
+                UpdateSource::Official,
+                InstallReason::Explicit,
+            )],
+            ..UpdateSnapshot::default()
+        };
+
+        let diff = SnapshotDiff::between(&previous(), &current);
+        assert_eq!(notification_body(&diff, &current, false), None);
+        assert_eq!(
+            notification_body(&diff, &current, true).as_deref(),
+            Some("1 update no longer pending (1 remaining)")
+        );
+    }
+
+    #[test]
+    fn notification_body_handles_unchanged_snapshot() {
+        let diff = SnapshotDiff::between(&previous(), &previous());
+        assert_eq!(notification_body(&diff, &previous(), true), None);
     }
 
     #[test]
-    fn notification_body_handles_zero_counts() {
-        let body = notification_body(0, 0);
-        assert_eq!(body, "Pending updates changed from 0 to 0");
+    fn header_leaves_out_empty_parts() {
+        let current = UpdateSnapshot {
+            official: vec![
+                update(
+                    "firefox",
+                    "3-1",
+                    UpdateSource::Official,
+                    InstallReason::Explicit,
+                ),
+                update(
+                    "icu",
+                    "2-1",
+                    UpdateSource::Official,
+                    InstallReason::Dependency,
+                ),
+            ],
+            ..UpdateSnapshot::default()
+        };
+
+        let diff = SnapshotDiff::between(&previous(), &current);
+        assert_eq!(
+            notification_body(&diff, &current, false).as_deref(),
+            Some("1 updated again (2 pending)\nOfficial: firefox 3-1")
+        );
     }
 
     #[test]
-    fn notification_test_sends_notification() {
-        let previous = 2;
-        let current = 5;
-        notify_count_change(previous, current);
+    fn changes_during_quiet_hours_are_announced_afterwards() {
+        let config = EffectiveConfig {
+            quiet_hours: vec!["22:00-07:00".parse().expect("window")],
+            ..EffectiveConfig::default()
+        };
+        let at = |hour| {
+            let time = NaiveTime::from_hms_opt(hour, 0, 0).expect("time");
+            let date = Local::now().date_naive();
+            Local
+                .from_local_datetime(&date.and_time(time))
+                .earliest()
+                .expect("local time")
+        };
+        let mut announcer = Announcer::default();
+        announcer.observe(&UpdateSnapshot::default());
+        assert_eq!(announcer.take_message(&config, at(21)), None);
+
+        announcer.observe(&previous());
+        assert_eq!(announcer.take_message(&config, at(23)), None);
+        assert_eq!(announcer.take_message(&config, at(3)), None);
+        assert_eq!(
+            announcer.take_message(&config, at(8)).as_deref(),
+            Some("2 new updates (2 pending)\nOfficial: firefox 2-1 + 1 dependency")
+        );
+        assert_eq!(announcer.take_message(&config, at(9)), None);
+    }
+
+    #[test]
+    fn snoozing_is_not_announced_as_a_decrease() {
+        let config = EffectiveConfig {
+            notify_on_decrease: true,
+            ..EffectiveConfig::default()
+        };
+        let now = Local::now();
+        let mut announcer = Announcer::default();
+        announcer.observe(&previous());
+        assert_eq!(announcer.take_message(&config, now), None);
+
+        let mut snoozed = previous();
+        snoozed.snoozed.push(snoozed.official.remove(0));
+        announcer.observe(&snoozed);
+        assert_eq!(announcer.take_message(&config, now), None);
+
+        snoozed.official.clear();
+        announcer.observe(&snoozed);
+        assert_eq!(
+            announcer.take_message(&config, now).as_deref(),
+            Some("1 update no longer pending (0 remaining)")
+        );
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::state::{PackageUpdate, UpdateSource};
 
 pub fn parse_update_lines(output: &str, source: UpdateSource) -> Vec<PackageUpdate> {
@@ -15,24 +17,67 @@ fn parse_update_line(line: &str, source: UpdateSource) -> Option<PackageUpdate>
         return None;
     }
 
-    let name = parts.first()?.to_string();
-    let current = parts.get(1)?.to_string();
+    let name = parts.first()?;
+    let current = parts.get(1)?;
 
     let latest = if let Some(arrow_idx) = parts.iter().position(|p| *p == "->") {
-        parts
-            .get(arrow_idx + 1)
-            .unwrap_or(parts.last()?)
-            .to_string()
+        parts.get(arrow_idx + 1).unwrap_or(parts.last()?)
     } else {
-        parts.last()?.to_string()
+        parts.last()?
     };
 
-    Some(PackageUpdate {
-        name,
-        current,
-        latest,
-        source,
-    })
+    Some(PackageUpdate::new(name, current, latest, source))
+}
+
+/// Repository and download size of a sync package.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncInfo {
+    pub repo: Option<String>,
+    pub download_size: Option<u64>,
+}
+
+/// Parses `pacman -Si` output (one blank-line separated record per package, `C` locale)
+/// into info keyed by package name.
+pub fn parse_sync_info(output: &str) -> HashMap<String, SyncInfo> {
+    let mut packages = HashMap::new();
+    let mut name: Option<String> = None;
+    let mut info = SyncInfo::default();
+
+    for line in output.lines().chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if let Some(name) = name.take() {
+                packages.insert(name, std::mem::take(&mut info));
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "Repository" => info.repo = Some(value.to_string()),
+            "Name" => name = Some(value.to_string()),
+            "Download Size" => info.download_size = parse_size(value),
+            _ => {}
+        }
+    }
+
+    packages
+}
+
+/// Parses sizes as pacman prints them, e.g. `1.50 MiB`.
+fn parse_size(raw: &str) -> Option<u64> {
+    let (number, unit) = raw.split_once(' ')?;
+    let number: f64 = number.parse().ok()?;
+    let factor = match unit.trim() {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * factor).round() as u64)
 }
 
 #[cfg(test)]
@@ -70,4 +115,32 @@ mod tests {
         assert_eq!(parsed.len(), 1);
         assert_eq!(parsed[0].name, "foo");
     }
+
+    #[test]
+    fn parses_repository_and_download_size() {
+        let input = "Repository      : core
+Name            : linux
+Version         : 6.9.1.arch1-1
+Description     : The Linux kernel and modules
+Download Size   : 135.02 MiB
+Installed Size  : 136.20 MiB
+
+Repository      : extra
+Name            : zstd
+Version         : 1.5.6-1
+Download Size   : 512.00 KiB
+";
+        let info = parse_sync_info(input);
+
+        assert_eq!(info.len(), 2);
+        assert_eq!(info["linux"].repo.as_deref(), Some("core"));
+        assert_eq!(info["linux"].download_size, Some(141_578_732));
+        assert_eq!(
+            info["zstd"],
+            SyncInfo {
+                repo: Some("extra".to_string()),
+                download_size: Some(524_288),
+            }
+        );
+    }
 }
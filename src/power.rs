@@ -0,0 +1,214 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bus::SystemBus;
+
+const PROFILES_SERVICE: &str = "net.hadess.PowerProfiles";
+const PROFILES_PATH: &str = "/net/hadess/PowerProfiles";
+const PROFILES_INTERFACE: &str = "net.hadess.PowerProfiles";
+
+/// What the power supplies under `/sys/class/power_supply` report right now.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PowerState {
+    /// A system battery is discharging and no mains adapter is online.
+    pub on_battery: bool,
+    /// Lowest charge among system batteries, in percent.
+    pub capacity: Option<u8>,
+}
+
+/// Battery-aware scheduling settings. A `threshold` of 0 disables the feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PowerPolicy {
+    pub threshold: u8,
+    pub poll_factor: u32,
+    pub skip_aur: bool,
+}
+
+impl Default for PowerPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 0,
+            poll_factor: 3,
+            skip_aur: true,
+        }
+    }
+}
+
+impl PowerPolicy {
+    pub fn enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// True when checks should be stretched: discharging below the threshold, or the
+    /// power-saver profile is active.
+    pub fn should_save(&self, state: PowerState, power_saver: bool) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+
+        let low_battery = state.on_battery
+            && state
+                .capacity
+                .is_none_or(|capacity| capacity < self.threshold);
+        low_battery || power_saver
+    }
+}
+
+/// Reads power supply state from sysfs and the active power-profiles-daemon profile.
+pub struct PowerMonitor {
+    sysfs_root: PathBuf,
+    system_bus: SystemBus,
+}
+
+impl PowerMonitor {
+    pub fn new(sysfs_root: PathBuf) -> Self {
+        Self {
+            sysfs_root,
+            system_bus: SystemBus::default(),
+        }
+    }
+
+    pub fn state(&self) -> PowerState {
+        read_power_state(&self.sysfs_root)
+    }
+
+    pub fn power_saver_active(&mut self) -> bool {
+        self.system_bus
+            .property::<String>(
+                PROFILES_SERVICE,
+                PROFILES_PATH,
+                PROFILES_INTERFACE,
+                "ActiveProfile",
+            )
+            .is_some_and(|profile| profile == "power-saver")
+    }
+}
+
+/// Scans `<root>` (normally `/sys/class/power_supply`). Peripheral batteries (`scope` is
+/// `Device`, e.g. a wireless mouse) are ignored.
+pub fn read_power_state(root: &Path) -> PowerState {
+    let Ok(entries) = fs::read_dir(root) else {
+        return PowerState::default();
+    };
+
+    let mut mains_online = false;
+    let mut discharging = false;
+    let mut capacity: Option<u8> = None;
+
+    for entry in entries.flatten() {
+        let supply = entry.path();
+        let attr = |name: &str| {
+            fs::read_to_string(supply.join(name))
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+
+        match attr("type").as_deref() {
+            Some("Mains") | Some("USB") => {
+                mains_online |= attr("online").as_deref() == Some("1");
+            }
+            Some("Battery") if attr("scope").as_deref() != Some("Device") => {
+                discharging |= attr("status").as_deref() == Some("Discharging");
+                if let Some(level) = attr("capacity").and_then(|raw| raw.parse::<u8>().ok()) {
+                    capacity = Some(capacity.map_or(level, |current| current.min(level)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    PowerState {
+        on_battery: discharging && !mains_online,
+        capacity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PowerPolicy {
+        PowerPolicy {
+            threshold: 50,
+            ..PowerPolicy::default()
+        }
+    }
+
+    fn write_supply(root: &Path, name: &str, attrs: &[(&str, &str)]) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).expect("create supply dir");
+        for (attr, value) in attrs {
+            fs::write(dir.join(attr), format!("{value}\n")).expect("write attr");
+        }
+    }
+
+    #[test]
+    fn discharging_battery_without_mains_is_on_battery() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        write_supply(temp.path(), "AC", &[("type", "Mains"), ("online", "0")]);
+        write_supply(
+            temp.path(),
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("capacity", "35"),
+            ],
+        );
+        write_supply(
+            temp.path(),
+            "hid-mouse-battery",
+            &[("type", "Battery"), ("scope", "Device"), ("capacity", "5")],
+        );
+
+        let state = read_power_state(temp.path());
+        assert_eq!(
+            state,
+            PowerState {
+                on_battery: true,
+                capacity: Some(35),
+            }
+        );
+        assert!(policy().should_save(state, false));
+    }
+
+    #[test]
+    fn mains_online_resumes_normal_cadence() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        write_supply(temp.path(), "AC", &[("type", "Mains"), ("online", "1")]);
+        write_supply(
+            temp.path(),
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Charging"),
+                ("capacity", "20"),
+            ],
+        );
+
+        let state = read_power_state(temp.path());
+        assert!(!state.on_battery);
+        assert!(!policy().should_save(state, false));
+    }
+
+    #[test]
+    fn threshold_and_power_saver_decide_saving() {
+        let policy = policy();
+        let charged = PowerState {
+            on_battery: true,
+            capacity: Some(80),
+        };
+
+        assert!(!policy.should_save(charged, false));
+        assert!(policy.should_save(PowerState::default(), true));
+
+        assert!(!PowerPolicy::default().should_save(charged, true));
+    }
+
+    #[test]
+    fn missing_sysfs_means_mains_power() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let state = read_power_state(&temp.path().join("missing"));
+        assert_eq!(state, PowerState::default());
+    }
+}
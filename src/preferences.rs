@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use gtk::prelude::*;
+use log::info;
+
+use crate::settings::{ConfigDocument, FIELDS, Field, FieldKind, FieldValue, Page, SettingsError};
+
+/// Input widget for one config key.
+enum Editor {
+    Toggle(gtk::CheckButton),
+    Number(gtk::SpinButton),
+    Choice(gtk::ComboBoxText),
+    Entry(gtk::Entry),
+    Icon { light: gtk::Entry, dark: gtk::Entry },
+}
+
+impl Editor {
+    fn new(field: &Field, value: &FieldValue) -> Self {
+        match (field.kind, value) {
+            (FieldKind::Bool { .. }, FieldValue::Bool(active)) => {
+                let check = gtk::CheckButton::with_label(field.label);
+                check.set_active(*active);
+                Self::Toggle(check)
+            }
+            (_, FieldValue::Integer(v)) => {
+                let (min, max) = field.range().unwrap_or((0, i64::from(i32::MAX)));
+                let spin = gtk::SpinButton::with_range(min as f64, max as f64, 1.0);
+                spin.set_value(*v as f64);
+                Self::Number(spin)
+            }
+            (FieldKind::Choice { options, .. }, FieldValue::Text(selected)) => {
+                let combo = gtk::ComboBoxText::new();
+                for option in options {
+                    combo.append(Some(option), option);
+                }
+                combo.set_active_id(Some(selected));
+                Self::Choice(combo)
+            }
+            (_, FieldValue::Icon { light, dark }) => {
+                let (light_entry, dark_entry) = (gtk::Entry::new(), gtk::Entry::new());
+                light_entry.set_text(light);
+                light_entry.set_placeholder_text(Some("theme icon or file"));
+                dark_entry.set_text(dark);
+                dark_entry.set_placeholder_text(Some("dark theme (optional)"));
+                Self::Icon {
+                    light: light_entry,
+                    dark: dark_entry,
+                }
+            }
+            (kind, value) => {
+                let entry = gtk::Entry::new();
+                if let FieldValue::Text(text) = value {
+                    entry.set_text(text);
+                }
+                if let FieldKind::Schedule = kind {
+                    entry.set_placeholder_text(Some("not set"));
+                }
+                Self::Entry(entry)
+            }
+        }
+    }
+
+    fn value(&self) -> FieldValue {
+        match self {
+            Self::Toggle(check) => FieldValue::Bool(check.is_active()),
+            Self::Number(spin) => FieldValue::Integer(i64::from(spin.value_as_int())),
+            Self::Choice(combo) => {
+                FieldValue::Text(combo.active_id().map(String::from).unwrap_or_default())
+            }
+            Self::Entry(entry) => FieldValue::Text(entry.text().to_string()),
+            Self::Icon { light, dark } => FieldValue::Icon {
+                light: light.text().to_string(),
+                dark: dark.text().to_string(),
+            },
+        }
+    }
+
+    /// Adds the editor to `grid` as row `row`, labelled unless it is a check box.
+    fn attach(&self, grid: &gtk::Grid, field: &Field, row: i32) {
+        let widget: gtk::Widget = match self {
+            Self::Toggle(check) => {
+                grid.attach(check, 0, row, 2, 1);
+                return;
+            }
+            Self::Number(spin) => spin.clone().upcast(),
+            Self::Choice(combo) => combo.clone().upcast(),
+            Self::Entry(entry) => entry.clone().upcast(),
+            Self::Icon { light, dark } => {
+                let row_box = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+                row_box.pack_start(light, true, true, 0);
+                row_box.pack_start(dark, true, true, 0);
+                row_box.upcast()
+            }
+        };
+        let label = gtk::Label::new(Some(field.label));
+        label.set_xalign(0.0);
+        widget.set_hexpand(true);
+        grid.attach(&label, 0, row, 1, 1);
+        grid.attach(&widget, 1, row, 1, 1);
+    }
+}
+
+/// Opens a dialog editing every key of the config file at `path`. "Save" rewrites the file,
+/// keeping its comments and any keys the dialog does not know, and calls `on_saved`;
+/// invalid values are reported in the dialog and nothing is written.
+pub fn open_preferences(path: &Path, on_saved: impl Fn() + 'static) -> gtk::Dialog {
+    let dialog = gtk::Dialog::new();
+    dialog.set_title("Pactrack preferences");
+    dialog.set_default_size(560, 480);
+    dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+    dialog.add_button("Save", gtk::ResponseType::Accept);
+
+    let error = gtk::Label::new(None);
+    error.set_xalign(0.0);
+    error.set_line_wrap(true);
+    error.set_selectable(true);
+
+    let document = ConfigDocument::open(path);
+    if let Err(err) = &document {
+        error.set_text(&format!(
+            "{err}\nFix the file by hand before editing it here."
+        ));
+        dialog.set_response_sensitive(gtk::ResponseType::Accept, false);
+    }
+
+    let notebook = gtk::Notebook::new();
+    let mut editors = Vec::with_capacity(FIELDS.len());
+    for page in Page::ALL {
+        let grid = gtk::Grid::new();
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(12);
+        grid.set_border_width(12);
+        for (row, field) in FIELDS.iter().filter(|f| f.page == page).enumerate() {
+            let value = match &document {
+                Ok(document) => document.value(field),
+                Err(_) => field.default_value(),
+            };
+            let editor = Editor::new(field, &value);
+            editor.attach(&grid, field, row as i32);
+            editors.push((field, editor, value));
+        }
+
+        let scrolled = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&grid);
+        notebook.append_page(&scrolled, Some(&gtk::Label::new(Some(page.title()))));
+    }
+
+    let content = dialog.content_area();
+    content.set_spacing(6);
+    content.set_border_width(8);
+    content.pack_start(&notebook, true, true, 0);
+    content.pack_start(&error, false, false, 0);
+
+    let path = path.to_path_buf();
+    let error_label = error.clone();
+    dialog.connect_response(move |dialog, response| {
+        if response != gtk::ResponseType::Accept {
+            dialog.close();
+            return;
+        }
+        match save(&path, &editors) {
+            Ok(()) => {
+                info!("saved preferences to {}", path.display());
+                dialog.close();
+                on_saved();
+            }
+            Err(err) => {
+                error_label.set_text(&format!("Not saved: {err}"));
+                error_label.set_visible(true);
+            }
+        }
+    });
+
+    dialog.show_all();
+    error.set_visible(document.is_err());
+    dialog.present();
+    dialog
+}
+
+/// Writes only the keys edited since the dialog opened, on top of the file as it is now, so
+/// changes made elsewhere in the meantime are kept.
+fn save(path: &Path, editors: &[(&Field, Editor, FieldValue)]) -> Result<(), SettingsError> {
+    let mut document = ConfigDocument::open(path)?;
+    for (field, editor, initial) in editors {
+        let value = editor.value();
+        if value != *initial {
+            document.set(field, &value)?;
+        }
+    }
+    document.save()
+}
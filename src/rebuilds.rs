@@ -0,0 +1,151 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::localdb::InstalledPackage;
+use crate::state::{PackageUpdate, RebuildHint};
+
+/// Builds the "possible rebuilds needed" hints for a set of official updates.
+///
+/// Watched packages always get a hint with their reverse-dependency count. Any other
+/// updated package only gets one when it provides sonames that installed foreign
+/// (AUR) packages depend on.
+pub fn find_rebuild_hints(
+    updates: &[PackageUpdate],
+    installed: &[InstalledPackage],
+    foreign: &HashSet<String>,
+    watch: &[String],
+) -> Vec<RebuildHint> {
+    let mut hints = Vec::new();
+
+    for update in updates {
+        let Some(package) = installed.iter().find(|pkg| pkg.name == update.name) else {
+            continue;
+        };
+
+        let watched = watch.contains(&update.name);
+        let sonames: HashSet<&str> = package.sonames().collect();
+        if !watched && sonames.is_empty() {
+            continue;
+        }
+
+        let mut provided: HashSet<&str> = package.provides.iter().map(String::as_str).collect();
+        provided.insert(package.name.as_str());
+
+        let dependents: Vec<&InstalledPackage> = installed
+            .iter()
+            .filter(|other| other.name != package.name)
+            .filter(|other| {
+                other
+                    .depends
+                    .iter()
+                    .any(|dep| provided.contains(dep.as_str()))
+            })
+            .collect();
+
+        let aur_dependents: BTreeSet<String> = dependents
+            .iter()
+            .filter(|other| foreign.contains(&other.name))
+            .filter(|other| {
+                watched
+                    || other
+                        .depends
+                        .iter()
+                        .any(|dep| sonames.contains(dep.as_str()))
+            })
+            .map(|other| other.name.clone())
+            .collect();
+
+        if !watched && aur_dependents.is_empty() {
+            continue;
+        }
+
+        hints.push(RebuildHint {
+            package: update.name.clone(),
+            current: update.current.clone(),
+            latest: update.latest.clone(),
+            dependents: dependents.len(),
+            aur_dependents: aur_dependents.into_iter().collect(),
+            minor_bump: is_minor_bump(&update.current, &update.latest),
+        });
+    }
+
+    hints
+}
+
+/// True when the `major.minor` prefix of the upstream version changes (`3.12.1-1` -> `3.13.0-1`).
+pub fn is_minor_bump(current: &str, latest: &str) -> bool {
+    major_minor(current) != major_minor(latest)
+}
+
+fn major_minor(version: &str) -> Vec<&str> {
+    let upstream = version.rsplit_once('-').map_or(version, |(v, _)| v);
+    let upstream = upstream.split_once(':').map_or(upstream, |(_, v)| v);
+    upstream.split('.').take(2).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::UpdateSource;
+
+    fn installed(name: &str, depends: &[&str], provides: &[&str]) -> InstalledPackage {
+        InstalledPackage {
+            name: name.to_string(),
+            version: "1-1".to_string(),
+            depends: depends.iter().map(|d| d.to_string()).collect(),
+            provides: provides.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn watched_package_reports_dependents_and_aur_packages() {
+        let updates = vec![PackageUpdate::new(
+            "python",
+            "3.12.1-1",
+            "3.13.0-1",
+            UpdateSource::Official,
+        )];
+        let local = vec![
+            installed("python", &[], &[]),
+            installed("python-requests", &["python"], &[]),
+            installed("python-foo-git", &["python"], &[]),
+            installed("vim", &[], &[]),
+        ];
+        let foreign = HashSet::from(["python-foo-git".to_string()]);
+
+        let hints = find_rebuild_hints(&updates, &local, &foreign, &["python".to_string()]);
+
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].dependents, 2);
+        assert_eq!(hints[0].aur_dependents, vec!["python-foo-git".to_string()]);
+        assert!(hints[0].minor_bump);
+    }
+
+    #[test]
+    fn unwatched_soname_provider_needs_an_aur_dependent() {
+        let updates = vec![
+            PackageUpdate::new("libfoo", "1.0-1", "1.0-2", UpdateSource::Official),
+            PackageUpdate::new("libbar", "1.0-1", "1.0-2", UpdateSource::Official),
+        ];
+        let local = vec![
+            installed("libfoo", &[], &["libfoo.so"]),
+            installed("libbar", &[], &["libbar.so"]),
+            installed("aur-app", &["libfoo.so"], &[]),
+            installed("repo-app", &["libbar.so"], &[]),
+        ];
+        let foreign = HashSet::from(["aur-app".to_string()]);
+
+        let hints = find_rebuild_hints(&updates, &local, &foreign, &[]);
+
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].package, "libfoo");
+        assert_eq!(hints[0].aur_dependents, vec!["aur-app".to_string()]);
+        assert!(!hints[0].minor_bump);
+    }
+
+    #[test]
+    fn minor_bump_ignores_epoch_and_pkgrel() {
+        assert!(is_minor_bump("1:74.1-1", "1:75.1-1"));
+        assert!(!is_minor_bump("2.39-1", "2.39-2"));
+        assert!(!is_minor_bump("3.12.1-1", "3.12.2-1"));
+    }
+}
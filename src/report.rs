@@ -0,0 +1,282 @@
+use chrono::{DateTime, Local};
+
+use crate::state::{AppState, PackageUpdate, RebuildHint, Status, UpdateSnapshot};
+
+const COLLAPSED_NAME_LIMIT: usize = 6;
+
+pub fn render_details(snapshot: &UpdateSnapshot, now: DateTime<Local>) -> String {
+    let mut lines = Vec::new();
+
+    render_section(&mut lines, "Official updates", &snapshot.official, now);
+    lines.push(String::new());
+    render_section(&mut lines, "AUR updates", &snapshot.aur, now);
+
+    if !snapshot.rebuilds.is_empty() {
+        lines.push(String::new());
+        render_rebuilds(&mut lines, &snapshot.rebuilds);
+    }
+
+    if !snapshot.snoozed.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("Snoozed ({})", snapshot.snoozed.len()));
+        for update in &snapshot.snoozed {
+            lines.push(format!(
+                "  {} {} -> {}",
+                update.name, update.current, update.latest
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn render_rebuilds(lines: &mut Vec<String>, hints: &[RebuildHint]) {
+    lines.push("Possible rebuilds needed".to_string());
+    for hint in hints {
+        let bump = if hint.minor_bump {
+            ", minor version bump"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "  {} {} -> {} ({} installed {}{bump})",
+            hint.package,
+            hint.current,
+            hint.latest,
+            hint.dependents,
+            plural(hint.dependents, "dependent", "dependents")
+        ));
+        if !hint.aur_dependents.is_empty() {
+            let names: Vec<&str> = hint.aur_dependents.iter().map(String::as_str).collect();
+            lines.push(format!(
+                "    AUR: {}",
+                summarize_names(&names, COLLAPSED_NAME_LIMIT)
+            ));
+        }
+    }
+}
+
+fn render_section(
+    lines: &mut Vec<String>,
+    title: &str,
+    updates: &[PackageUpdate],
+    now: DateTime<Local>,
+) {
+    lines.push(format!("{title} ({})", updates.len()));
+    if updates.is_empty() {
+        lines.push("  none".to_string());
+        return;
+    }
+
+    let (explicit, dependencies): (Vec<&PackageUpdate>, Vec<&PackageUpdate>) =
+        updates.iter().partition(|update| update.is_explicit());
+
+    for update in &explicit {
+        let mut line = format!("  {} {} -> {}", update.name, update.current, update.latest);
+        if let Some(pending) = pending_label(update, now) {
+            line.push_str(&format!(" ({pending})"));
+        }
+        lines.push(line);
+    }
+
+    if !dependencies.is_empty() {
+        let names: Vec<&str> = dependencies.iter().map(|u| u.name.as_str()).collect();
+        lines.push(format!(
+            "  + {} dependency {}: {}",
+            dependencies.len(),
+            plural(dependencies.len(), "update", "updates"),
+            summarize_names(&names, COLLAPSED_NAME_LIMIT)
+        ));
+    }
+}
+
+/// Human-readable age such as "pending for 9 days"; `None` for updates seen today.
+pub fn pending_label(update: &PackageUpdate, now: DateTime<Local>) -> Option<String> {
+    let days = update.pending_days(now).filter(|days| *days > 0)?;
+    Some(pending_days_label(days))
+}
+
+pub fn pending_days_label(days: i64) -> String {
+    format!(
+        "pending for {days} {}",
+        plural(days.unsigned_abs() as usize, "day", "days")
+    )
+}
+
+/// Binary size as pacman shows it, e.g. "1.5 MiB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Fills an indicator label template: `{total}`, `{official}` and `{aur}` are replaced by
+/// the pending counts, anything else is kept as written.
+pub fn render_label(template: &str, official: usize, aur: usize) -> String {
+    template
+        .replace("{total}", &(official + aur).to_string())
+        .replace("{official}", &official.to_string())
+        .replace("{aur}", &aur.to_string())
+}
+
+/// One-line summary of the app state, e.g. "3 updates available".
+pub fn status_text(state: &AppState) -> String {
+    let text = match state.status {
+        Status::Checking => "checking".to_string(),
+        Status::UpToDate => "up to date".to_string(),
+        Status::UpdatesAvailable => format!("{} updates available", state.total_count),
+        Status::Overdue => format!("{} updates available (overdue)", state.total_count),
+        Status::Offline if state.metered => "paused on metered connection".to_string(),
+        Status::Offline => "offline".to_string(),
+        Status::Error => {
+            let msg = state
+                .last_error
+                .as_deref()
+                .map(truncate_error)
+                .unwrap_or_else(|| "unknown error".to_string());
+            match state.consecutive_failures {
+                0 | 1 => format!("error ({msg})"),
+                n => format!("error, {n} failed checks ({msg})"),
+            }
+        }
+    };
+
+    let failed = state.failed_sources();
+    let partial = matches!(
+        state.status,
+        Status::UpToDate | Status::UpdatesAvailable | Status::Overdue
+    );
+    if partial && !failed.is_empty() {
+        let labels: Vec<&str> = failed.iter().map(|source| source.label()).collect();
+        format!("{text} ({} check failed)", labels.join(" and "))
+    } else {
+        text
+    }
+}
+
+pub fn truncate_error(msg: &str) -> String {
+    truncate(msg, 72)
+}
+
+pub fn truncate(msg: &str, max: usize) -> String {
+    if msg.chars().count() <= max {
+        msg.to_string()
+    } else {
+        msg.chars().take(max).collect::<String>() + "..."
+    }
+}
+
+pub fn summarize_names(names: &[&str], limit: usize) -> String {
+    if names.len() <= limit {
+        return names.join(", ");
+    }
+
+    format!(
+        "{}, and {} more",
+        names[..limit].join(", "),
+        names.len() - limit
+    )
+}
+
+pub fn plural<'a>(count: usize, one: &'a str, many: &'a str) -> &'a str {
+    if count == 1 { one } else { many }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{InstallReason, UpdateSource};
+
+    fn update(name: &str, reason: InstallReason) -> PackageUpdate {
+        let mut update = PackageUpdate::new(name, "1-1", "2-1", UpdateSource::Official);
+        update.reason = reason;
+        update
+    }
+
+    #[test]
+    fn collapses_dependency_updates_into_one_line() {
+        let snapshot = UpdateSnapshot {
+            official: vec![
+                update("firefox", InstallReason::Explicit),
+                update("icu", InstallReason::Dependency),
+                update("libxml2", InstallReason::Dependency),
+            ],
+            ..UpdateSnapshot::default()
+        };
+
+        let details = render_details(&snapshot, Local::now());
+        assert_eq!(
+            details,
+            "Official updates (3)\n  firefox 1-1 -> 2-1\n  + 2 dependency updates: icu, libxml2\n\nAUR updates (0)\n  none"
+        );
+    }
+
+    #[test]
+    fn lists_rebuild_hints_after_updates() {
+        let snapshot = UpdateSnapshot {
+            rebuilds: vec![RebuildHint {
+                package: "python".to_string(),
+                current: "3.12.1-1".to_string(),
+                latest: "3.13.0-1".to_string(),
+                dependents: 42,
+                aur_dependents: vec!["python-foo-git".to_string()],
+                minor_bump: true,
+            }],
+            ..UpdateSnapshot::default()
+        };
+
+        let details = render_details(&snapshot, Local::now());
+        assert!(details.ends_with(
+            "Possible rebuilds needed\n  python 3.12.1-1 -> 3.13.0-1 (42 installed dependents, minor version bump)\n    AUR: python-foo-git"
+        ));
+    }
+
+    #[test]
+    fn shows_pending_age_for_older_updates() {
+        let now = Local::now();
+        let mut old = update("firefox", InstallReason::Explicit);
+        old.first_seen = Some(now - chrono::Duration::days(9));
+        let mut fresh = update("neovim", InstallReason::Explicit);
+        fresh.first_seen = Some(now);
+
+        let snapshot = UpdateSnapshot {
+            official: vec![old, fresh],
+            ..UpdateSnapshot::default()
+        };
+
+        let details = render_details(&snapshot, now);
+        assert!(
+            details.contains("  firefox 1-1 -> 2-1 (pending for 9 days)\n  neovim 1-1 -> 2-1\n")
+        );
+    }
+
+    #[test]
+    fn label_template_fills_in_counts() {
+        assert_eq!(render_label("{total}", 3, 2), "5");
+        assert_eq!(render_label("{official}/{aur}", 3, 2), "3/2");
+        assert_eq!(render_label("{total} {unknown}", 1, 0), "1 {unknown}");
+    }
+
+    #[test]
+    fn summarize_names_truncates_long_lists() {
+        let names = ["a", "b", "c", "d"];
+        assert_eq!(summarize_names(&names, 2), "a, b, and 2 more");
+        assert_eq!(summarize_names(&names, 4), "a, b, c, d");
+    }
+
+    #[test]
+    fn formats_sizes_with_binary_units() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(141_578_732), "135.0 MiB");
+    }
+}
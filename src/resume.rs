@@ -0,0 +1,117 @@
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use log::{debug, info, warn};
+use zbus::blocking::{Connection, Proxy};
+
+use crate::scheduler::SchedulerCommand;
+
+const LOGIND_SERVICE: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_INTERFACE: &str = "org.freedesktop.login1.Manager";
+
+/// Grace period after resume so Wi-Fi has a chance to reconnect before the check runs.
+const RESUME_DELAY: Duration = Duration::from_secs(5);
+
+/// Subscribes to logind's `PrepareForSleep` signal and sends `SchedulerCommand::Resumed`
+/// shortly after each resume. Returns false when logind is unreachable, in which case the
+/// scheduler falls back to [`ClockJumpDetector`].
+pub fn watch_logind_resume(commands: Sender<SchedulerCommand>) -> bool {
+    let signals = Connection::system().and_then(|conn| {
+        Proxy::new(&conn, LOGIND_SERVICE, LOGIND_PATH, LOGIND_INTERFACE)?
+            .receive_signal("PrepareForSleep")
+    });
+
+    let signals = match signals {
+        Ok(signals) => signals,
+        Err(err) => {
+            warn!("cannot subscribe to logind sleep signals ({err}); detecting clock jumps");
+            return false;
+        }
+    };
+
+    thread::spawn(move || {
+        for message in signals {
+            // The argument is true when going to sleep and false after waking up.
+            match message.body().deserialize::<bool>() {
+                Ok(false) => {
+                    info!("resumed from suspend");
+                    thread::sleep(RESUME_DELAY);
+                    if commands.send(SchedulerCommand::Resumed).is_err() {
+                        break;
+                    }
+                }
+                Ok(true) => debug!("preparing for sleep"),
+                Err(err) => debug!("unexpected PrepareForSleep payload: {err}"),
+            }
+        }
+        debug!("logind signal stream ended");
+    });
+    true
+}
+
+/// Notices suspend by comparing wall-clock time against the monotonic clock, which does
+/// not advance while the machine sleeps.
+pub struct ClockJumpDetector {
+    threshold: Duration,
+    last: Option<(Instant, DateTime<Local>)>,
+}
+
+impl ClockJumpDetector {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            last: None,
+        }
+    }
+
+    /// Records a loop iteration; returns true when wall-clock time moved ahead of the
+    /// monotonic clock by more than the threshold since the previous call.
+    pub fn observe(&mut self, instant: Instant, wall: DateTime<Local>) -> bool {
+        let jumped = self.last.is_some_and(|(last_instant, last_wall)| {
+            let monotonic = instant.saturating_duration_since(last_instant);
+            let elapsed = (wall - last_wall).to_std().unwrap_or(Duration::ZERO);
+            elapsed.saturating_sub(monotonic) > self.threshold
+        });
+
+        self.last = Some((instant, wall));
+        jumped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_wall_clock_jump_beyond_threshold() {
+        let mut detector = ClockJumpDetector::new(Duration::from_secs(90));
+        let start = Instant::now();
+        let wall = Local::now();
+
+        assert!(!detector.observe(start, wall));
+        assert!(!detector.observe(
+            start + Duration::from_secs(60),
+            wall + chrono::Duration::seconds(61)
+        ));
+        assert!(detector.observe(
+            start + Duration::from_secs(120),
+            wall + chrono::Duration::hours(8)
+        ));
+    }
+
+    #[test]
+    fn backwards_clock_changes_are_not_resumes() {
+        let mut detector = ClockJumpDetector::new(Duration::from_secs(90));
+        let start = Instant::now();
+        let wall = Local::now();
+
+        detector.observe(start, wall);
+        assert!(!detector.observe(
+            start + Duration::from_secs(60),
+            wall - chrono::Duration::hours(1)
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+/// Time source for the scheduler, swappable in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// How failed checks are retried: a few fast attempts with exponential backoff and jitter,
+/// then a growing "degraded" delay between scheduled attempts until a check succeeds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub fast_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of each delay that is randomized (0.0 disables jitter).
+    pub jitter: f64,
+    pub degraded_interval: Duration,
+    pub max_degraded_interval: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            fast_retries: 3,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(120),
+            jitter: 0.2,
+            degraded_interval: Duration::from_secs(5 * 60),
+            max_degraded_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before fast retry number `attempt` (0-based). `sample` is a value in [0, 1)
+    /// that picks where in the jitter range the delay lands.
+    pub fn backoff_delay(&self, attempt: u32, sample: f64) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        self.apply_jitter(exp, sample)
+    }
+
+    /// Delay until the next attempt after `failures` consecutive failed checks.
+    pub fn degraded_delay(&self, failures: u32) -> Duration {
+        self.degraded_interval
+            .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+            .min(self.max_degraded_interval)
+    }
+
+    fn apply_jitter(&self, delay: Duration, sample: f64) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (2.0 * sample.clamp(0.0, 1.0) - 1.0);
+        delay.mul_f64(factor)
+    }
+}
+
+/// Cheap pseudo-random value in [0, 1) derived from the clock, good enough to spread
+/// retries of several machines hitting the same mirror.
+pub fn jitter_sample(now: DateTime<Local>) -> f64 {
+    let mut x = now.timestamp_subsec_nanos() as u64 ^ 0x9e37_79b9_7f4a_7c15;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    (x % 10_000) as f64 / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_and_jittered_within_range() {
+        let policy = RetryPolicy {
+            jitter: 0.5,
+            ..RetryPolicy::default()
+        };
+
+        assert_eq!(policy.backoff_delay(10, 0.5), Duration::from_secs(120));
+        assert_eq!(policy.backoff_delay(0, 0.0), Duration::from_secs(5));
+        assert_eq!(policy.backoff_delay(0, 1.0), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn degraded_delay_grows_until_capped() {
+        let policy = policy();
+        assert_eq!(policy.degraded_delay(1), Duration::from_secs(300));
+        assert_eq!(policy.degraded_delay(2), Duration::from_secs(600));
+        assert_eq!(policy.degraded_delay(10), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,381 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Timelike};
+use thiserror::Error;
+
+/// How far ahead a cron expression is searched before giving up (covers leap days).
+const CRON_SEARCH_DAYS: i64 = 366 * 4;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ScheduleError {
+    #[error("invalid cron expression `{0}`: expected 5 fields (minute hour day month weekday)")]
+    FieldCount(String),
+    #[error("invalid cron field `{field}` in `{expr}`")]
+    Field { expr: String, field: String },
+    #[error("invalid time `{0}` (expected HH:MM)")]
+    Time(String),
+    #[error("daily schedule needs at least one time")]
+    EmptyDaily,
+    #[error("invalid quiet hours window `{0}` (expected HH:MM-HH:MM)")]
+    Window(String),
+}
+
+/// When periodic checks run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    Interval(Duration),
+    Cron(CronExpr),
+    Daily(Vec<NaiveTime>),
+}
+
+impl Schedule {
+    pub fn every_minutes(minutes: u64) -> Self {
+        Self::Interval(Duration::minutes(minutes.max(1) as i64))
+    }
+
+    pub fn daily(times: &[String]) -> Result<Self, ScheduleError> {
+        let mut parsed = times
+            .iter()
+            .map(|raw| parse_time(raw))
+            .collect::<Result<Vec<_>, _>>()?;
+        if parsed.is_empty() {
+            return Err(ScheduleError::EmptyDaily);
+        }
+
+        parsed.sort();
+        parsed.dedup();
+        Ok(Self::Daily(parsed))
+    }
+
+    /// The first scheduled time strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Self::Interval(interval) => Some(after + *interval),
+            Self::Cron(expr) => expr.next_after(after),
+            Self::Daily(times) => (0..=2).find_map(|offset| {
+                let date = after.date_naive() + Duration::days(offset);
+                times
+                    .iter()
+                    .filter_map(|time| local_datetime(date, *time))
+                    .find(|candidate| *candidate > after)
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Interval(interval) => write!(f, "every {} minutes", interval.num_minutes()),
+            Self::Cron(expr) => write!(f, "cron `{}`", expr.source),
+            Self::Daily(times) => {
+                let times: Vec<String> = times
+                    .iter()
+                    .map(|time| time.format("%H:%M").to_string())
+                    .collect();
+                write!(f, "daily at {}", times.join(", "))
+            }
+        }
+    }
+}
+
+/// A standard five-field cron expression (`minute hour day-of-month month day-of-week`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronExpr {
+    source: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl FromStr for CronExpr {
+    type Err = ScheduleError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = raw.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields.as_slice() else {
+            return Err(ScheduleError::FieldCount(raw.to_string()));
+        };
+
+        let field = |value: &str, min: u32, max: u32| {
+            parse_cron_field(value, min, max).ok_or_else(|| ScheduleError::Field {
+                expr: raw.to_string(),
+                field: value.to_string(),
+            })
+        };
+
+        let mut days_of_week = field(dow, 0, 7)?;
+        // Both 0 and 7 mean Sunday.
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(Self {
+            source: raw.trim().to_string(),
+            minutes: field(minute, 0, 59)?,
+            hours: field(hour, 0, 23)?,
+            days_of_month: field(dom, 1, 31)?,
+            months: field(month, 1, 12)?,
+            days_of_week,
+            // Like vixie cron, a field starting with `*` (e.g. `*/2`) does not restrict the day.
+            dom_restricted: !dom.starts_with('*'),
+            dow_restricted: !dow.starts_with('*'),
+        })
+    }
+}
+
+impl CronExpr {
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.date_naive();
+        for offset in 0..CRON_SEARCH_DAYS {
+            let date = start + Duration::days(offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+
+            for hour in (0..24).filter(|h| self.hours[*h as usize]) {
+                for minute in (0..60).filter(|m| self.minutes[*m as usize]) {
+                    let Some(time) = NaiveTime::from_hms_opt(hour, minute, 0) else {
+                        continue;
+                    };
+                    if let Some(candidate) = local_datetime(date, time)
+                        && candidate > after
+                    {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.months[date.month() as usize] {
+            return false;
+        }
+
+        let dom = self.days_of_month[date.day() as usize];
+        let dow = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+
+        // Classic cron semantics: when both day fields are restricted, either may match.
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+/// Parses one cron field into a lookup table indexed by value (`max + 1` entries).
+fn parse_cron_field(raw: &str, min: u32, max: u32) -> Option<Vec<bool>> {
+    let mut allowed = vec![false; max as usize + 1];
+
+    for part in raw.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0)?),
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse().ok()?, end.parse().ok()?)
+        } else {
+            let value = range.parse().ok()?;
+            // `5/15` means "from 5 to the end of the range, every 15".
+            let end = if part.contains('/') { max } else { value };
+            (value, end)
+        };
+
+        if start < min || end > max || start > end {
+            return None;
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+
+    Some(allowed)
+}
+
+/// A daily window during which no scheduled checks run and no notifications are shown.
+/// Windows may wrap around midnight (`22:00-07:00`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl FromStr for QuietHours {
+    type Err = ScheduleError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let (start, end) = raw
+            .split_once('-')
+            .ok_or_else(|| ScheduleError::Window(raw.to_string()))?;
+        let start = parse_time(start).map_err(|_| ScheduleError::Window(raw.to_string()))?;
+        let end = parse_time(end).map_err(|_| ScheduleError::Window(raw.to_string()))?;
+        if start == end {
+            return Err(ScheduleError::Window(raw.to_string()));
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+impl QuietHours {
+    pub fn contains(&self, at: DateTime<Local>) -> bool {
+        let time = at.time();
+        if self.start < self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// The moment the window containing `at` ends.
+    fn end_after(&self, at: DateTime<Local>) -> Option<DateTime<Local>> {
+        (0..=1).find_map(|offset| {
+            local_datetime(at.date_naive() + Duration::days(offset), self.end)
+                .filter(|end| *end > at)
+        })
+    }
+}
+
+pub fn is_quiet(windows: &[QuietHours], at: DateTime<Local>) -> bool {
+    windows.iter().any(|window| window.contains(at))
+}
+
+/// The next scheduled check after `after`, pushed to the end of any quiet window it falls in.
+pub fn next_check(
+    schedule: &Schedule,
+    quiet_hours: &[QuietHours],
+    after: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    after_quiet_hours(quiet_hours, schedule.next_after(after)?)
+}
+
+/// `at`, or the end of the quiet hours it falls into.
+pub fn after_quiet_hours(
+    quiet_hours: &[QuietHours],
+    mut at: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    // Windows may overlap or chain; a handful of hops is always enough.
+    for _ in 0..quiet_hours.len() {
+        match quiet_hours.iter().find(|window| window.contains(at)) {
+            Some(window) => at = window.end_after(at)?,
+            None => break,
+        }
+    }
+
+    Some(at)
+}
+
+fn parse_time(raw: &str) -> Result<NaiveTime, ScheduleError> {
+    NaiveTime::parse_from_str(raw.trim(), "%H:%M").map_err(|_| ScheduleError::Time(raw.to_string()))
+}
+
+fn local_datetime(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Local>> {
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|dt| dt.with_nanosecond(0).unwrap_or(dt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> DateTime<Local> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").expect("date");
+        local_datetime(date, parse_time(time).expect("time")).expect("local time")
+    }
+
+    #[test]
+    fn cron_every_two_hours_on_the_hour() {
+        let expr: CronExpr = "0 */2 * * *".parse().expect("cron");
+        let next = expr.next_after(at("2024-03-05", "13:10")).expect("next");
+        assert_eq!(next, at("2024-03-05", "14:00"));
+
+        let next = expr.next_after(at("2024-03-05", "23:00")).expect("next");
+        assert_eq!(next, at("2024-03-06", "00:00"));
+    }
+
+    #[test]
+    fn cron_lists_ranges_and_weekdays() {
+        // 09:30 on weekdays only; 2024-03-08 is a Friday.
+        let expr: CronExpr = "30 9 * * 1-5".parse().expect("cron");
+        let next = expr.next_after(at("2024-03-08", "10:00")).expect("next");
+        assert_eq!(next, at("2024-03-11", "09:30"));
+
+        let expr: CronExpr = "15,45 8 1 * 7".parse().expect("cron");
+        // Day-of-month OR Sunday: 2024-03-03 is a Sunday.
+        let next = expr.next_after(at("2024-03-02", "12:00")).expect("next");
+        assert_eq!(next, at("2024-03-03", "08:15"));
+
+        // `*/2` is unrestricted like `*`, so only the weekday applies: Mondays.
+        let expr: CronExpr = "0 12 */2 * 1".parse().expect("cron");
+        let next = expr.next_after(at("2024-03-02", "13:00")).expect("next");
+        assert_eq!(next, at("2024-03-04", "12:00"));
+    }
+
+    #[test]
+    fn cron_rejects_malformed_expressions() {
+        assert!(matches!(
+            "0 */2 * *".parse::<CronExpr>(),
+            Err(ScheduleError::FieldCount(_))
+        ));
+        assert!(matches!(
+            "61 * * * *".parse::<CronExpr>(),
+            Err(ScheduleError::Field { .. })
+        ));
+        assert!(matches!(
+            "*/0 * * * *".parse::<CronExpr>(),
+            Err(ScheduleError::Field { .. })
+        ));
+    }
+
+    #[test]
+    fn daily_times_wrap_to_next_day() {
+        let schedule = Schedule::daily(&["18:30".to_string(), "08:00".to_string()]).expect("daily");
+        assert_eq!(
+            schedule.next_after(at("2024-03-05", "09:00")),
+            Some(at("2024-03-05", "18:30"))
+        );
+        assert_eq!(
+            schedule.next_after(at("2024-03-05", "19:00")),
+            Some(at("2024-03-06", "08:00"))
+        );
+    }
+
+    #[test]
+    fn quiet_hours_wrap_midnight_and_defer_checks() {
+        let quiet: QuietHours = "22:00-07:00".parse().expect("window");
+        assert!(quiet.contains(at("2024-03-05", "23:30")));
+        assert!(quiet.contains(at("2024-03-06", "06:59")));
+        assert!(!quiet.contains(at("2024-03-06", "07:00")));
+
+        let schedule = Schedule::every_minutes(30);
+        let next = next_check(&schedule, &[quiet], at("2024-03-05", "21:45"));
+        assert_eq!(next, Some(at("2024-03-06", "07:00")));
+
+        let next = next_check(&schedule, &[quiet], at("2024-03-06", "08:00"));
+        assert_eq!(next, Some(at("2024-03-06", "08:30")));
+    }
+
+    #[test]
+    fn quiet_hours_reject_bad_windows() {
+        assert!("22:00".parse::<QuietHours>().is_err());
+        assert!("22:00-22:00".parse::<QuietHours>().is_err());
+        assert!("25:00-07:00".parse::<QuietHours>().is_err());
+    }
+}
@@ -1,74 +1,274 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use chrono::Local;
+use chrono::{DateTime, Local};
+use inotify::WatchMask;
 use log::{debug, info, warn};
 
-use crate::commands::{DetectedAurHelper, perform_check};
-use crate::config::EffectiveConfig;
-use crate::state::{AppState, UpdateSnapshot};
+use crate::cache::{CachedCheck, default_cache_path, load_cached_check, save_cached_check};
+use crate::commands::{
+    CancelToken, CommandError, DetectedAurHelper, RunLimits, SourceCheck, check_source,
+    detect_aur_helper, recompute_local, resolve_pacman_db_path,
+};
+use crate::config::{ConfigSource, EffectiveConfig};
+use crate::history::{PendingHistory, default_history_path};
+use crate::network::{Connectivity, ConnectivityProbe};
+use crate::power::PowerMonitor;
+use crate::resume::{ClockJumpDetector, watch_logind_resume};
+use crate::retry::{Clock, SystemClock, jitter_sample};
+use crate::schedule::{after_quiet_hours, is_quiet, next_check};
+use crate::state::{AppState, SourceState, Status, UpdateSnapshot, UpdateSource};
+use crate::watcher::{WatchSpec, spawn_debounced_watcher};
 
 #[derive(Debug, Clone)]
 pub struct SchedulerUpdate {
     pub state: AppState,
     pub snapshot: Option<UpdateSnapshot>,
     pub helper: Option<DetectedAurHelper>,
+    /// The new config after the file was reloaded successfully.
+    pub config: Option<EffectiveConfig>,
+    /// Why the latest reload failed; the previous config stays active meanwhile.
+    pub config_error: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum SchedulerCommand {
     RefreshNow,
+    /// The machine woke from suspend; runs a check unless offline.
+    Resumed,
+    /// Packages were installed or removed outside pactrack; recomputes without syncing.
+    LocalDbChanged,
+    /// The config file changed; carries the reloaded config or the reason it was rejected.
+    Reconfigure(Result<Box<EffectiveConfig>, String>),
+    /// Hides one pending version from the counts for `SNOOZE_DURATION`.
+    Snooze {
+        source: UpdateSource,
+        name: String,
+        latest: String,
+    },
+    /// Aborts the checks in flight, killing their commands; nothing happens when idle.
+    Cancel,
+    /// A source check finished on its worker thread. `run` identifies the check, so results
+    /// of a cancelled check that arrive late are ignored.
+    SourceDone {
+        source: UpdateSource,
+        run: u64,
+        result: Result<SourceCheck, CommandError>,
+    },
     Quit,
 }
 
 pub fn start_scheduler(
     config: EffectiveConfig,
+    config_source: ConfigSource,
     updates_tx: Sender<SchedulerUpdate>,
 ) -> Sender<SchedulerCommand> {
     let (cmd_tx, cmd_rx) = mpsc::channel::<SchedulerCommand>();
 
-    thread::spawn(move || run_scheduler(config, cmd_rx, updates_tx));
+    if config.watch_local_db
+        && let Err(err) = watch_local_db(cmd_tx.clone())
+    {
+        warn!("cannot watch the local pacman database: {err}");
+    }
+    if let Err(err) = watch_config(config_source.clone(), cmd_tx.clone()) {
+        warn!(
+            "cannot watch {} for changes: {err}",
+            config_source.path.display()
+        );
+    }
+    let resume_jumps = (!watch_logind_resume(cmd_tx.clone()))
+        .then(|| ClockJumpDetector::new(SUSPEND_JUMP_THRESHOLD));
+    let results_tx = cmd_tx.clone();
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    let power = PowerMonitor::new(PathBuf::from(POWER_SUPPLY_ROOT));
+    thread::spawn(move || {
+        run_scheduler(
+            config,
+            clock,
+            power,
+            cmd_rx,
+            results_tx,
+            updates_tx,
+            resume_jumps,
+        )
+    });
     cmd_tx
 }
 
+/// Sends `LocalDbChanged` once a pacman transaction finishes: the package entries under
+/// `local/` change and `db.lck` goes away.
+fn watch_local_db(commands: Sender<SchedulerCommand>) -> io::Result<()> {
+    let db_path = resolve_pacman_db_path();
+    let specs = vec![
+        WatchSpec {
+            dir: db_path.join("local"),
+            mask: WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_TO,
+            names: Vec::new(),
+        },
+        WatchSpec {
+            dir: db_path.clone(),
+            mask: WatchMask::DELETE,
+            names: vec![OsString::from("db.lck")],
+        },
+    ];
+
+    spawn_debounced_watcher(specs, LOCAL_DB_DEBOUNCE, move || {
+        // Still mid-transaction; the lock removal will fire again once it is done.
+        if db_path.join("db.lck").exists() {
+            return true;
+        }
+        commands.send(SchedulerCommand::LocalDbChanged).is_ok()
+    })
+}
+
+/// Reloads the config whenever the file is written or replaced (editors often save through a
+/// rename, so the directory is watched rather than the file) and sends the result. The
+/// directory is created first, so a config written later is still picked up.
+fn watch_config(source: ConfigSource, commands: Sender<SchedulerCommand>) -> io::Result<()> {
+    let (Some(dir), Some(name)) = (source.path.parent(), source.path.file_name()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "config path has no file name",
+        ));
+    };
+    fs::create_dir_all(dir)?;
+    let specs = vec![WatchSpec {
+        dir: dir.to_path_buf(),
+        mask: WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::DELETE,
+        names: vec![name.to_os_string()],
+    }];
+
+    spawn_debounced_watcher(specs, CONFIG_DEBOUNCE, move || {
+        let result = source.load().map(Box::new).map_err(|err| err.to_string());
+        commands.send(SchedulerCommand::Reconfigure(result)).is_ok()
+    })
+}
+
+/// `results_tx` feeds the same channel as `commands`; check workers report through it. The
+/// clock and power monitor are passed in so tests can fake time and `/sys/class/power_supply`.
 fn run_scheduler(
     config: EffectiveConfig,
+    clock: Arc<dyn Clock>,
+    power: PowerMonitor,
     commands: Receiver<SchedulerCommand>,
+    results_tx: Sender<SchedulerCommand>,
     updates_tx: Sender<SchedulerUpdate>,
+    mut resume_jumps: Option<ClockJumpDetector>,
 ) {
-    let interval = Duration::from_secs(config.poll_minutes.max(1) * 60);
-    let mut last_state = AppState::default();
-    let mut last_helper: Option<DetectedAurHelper> = None;
-
-    run_once(
-        &config,
-        &updates_tx,
-        &mut last_state,
-        &mut last_helper,
-        "startup",
+    info!(
+        "scheduling official checks {} and AUR checks {} ({} quiet hour windows)",
+        config.schedule_for(UpdateSource::Official),
+        config.schedule_for(UpdateSource::Aur),
+        config.quiet_hours.len()
+    );
+    let connectivity = ConnectivityProbe::new(
+        PathBuf::from("/proc"),
+        config.offline_detection,
+        config.skip_when_metered,
     );
+    let mut scheduler = Scheduler {
+        config,
+        updates_tx,
+        last_state: AppState::default(),
+        last_helper: None,
+        history: PendingHistory::load(default_history_path()),
+        cache_path: default_cache_path(),
+        clock,
+        connectivity,
+        offline: false,
+        power,
+        power_saving: false,
+        last_snapshot: None,
+        official: SourceState::default(),
+        aur: SourceState::default(),
+        results_tx,
+        running: HashMap::new(),
+        retrying: HashMap::new(),
+        local_db_changed: false,
+        next_run: 0,
+        config_error: None,
+    };
+
+    scheduler.update_power_mode();
+
+    scheduler.restore_cached();
+    let now = scheduler.clock.now();
+    if is_quiet(&scheduler.config.quiet_hours, now) {
+        info!("in quiet hours, deferring the startup check");
+        for source in scheduler.enabled_sources() {
+            scheduler.plan_next_check(source, now);
+        }
+        scheduler.publish(None);
+    } else {
+        scheduler.run_when_online("startup", &scheduler.enabled_sources());
+    }
 
     loop {
-        match commands.recv_timeout(interval) {
-            Ok(SchedulerCommand::RefreshNow) => run_once(
-                &config,
-                &updates_tx,
-                &mut last_state,
-                &mut last_helper,
-                "manual-refresh",
-            ),
+        let received = commands.recv_timeout(scheduler.wait_duration(scheduler.clock.now()));
+
+        // Without logind, a suspend shows up as wall-clock time racing ahead of the
+        // monotonic clock between two wake-ups of this loop.
+        if let Some(detector) = resume_jumps.as_mut()
+            && detector.observe(Instant::now(), scheduler.clock.now())
+        {
+            info!("wall clock jumped ahead, assuming resume from suspend");
+            scheduler.run_when_online("resume", &scheduler.enabled_sources());
+        }
+
+        match received {
+            Ok(SchedulerCommand::RefreshNow) => {
+                scheduler.run_sources("manual-refresh", &scheduler.enabled_sources())
+            }
+            Ok(SchedulerCommand::Resumed) => {
+                scheduler.run_when_online("resume", &scheduler.enabled_sources())
+            }
+            Ok(SchedulerCommand::LocalDbChanged) => scheduler.recompute_local(),
+            Ok(SchedulerCommand::Reconfigure(result)) => scheduler.reconfigure(result),
+            Ok(SchedulerCommand::Snooze {
+                source,
+                name,
+                latest,
+            }) => scheduler.snooze(source, &name, &latest),
+            Ok(SchedulerCommand::Cancel) => scheduler.cancel(),
+            Ok(SchedulerCommand::SourceDone {
+                source,
+                run,
+                result,
+            }) => scheduler.finish_source(source, run, result),
             Ok(SchedulerCommand::Quit) => {
                 info!("scheduler received quit command");
+                for run in scheduler.running.values() {
+                    run.cancel.cancel();
+                }
                 break;
             }
-            Err(RecvTimeoutError::Timeout) => run_once(
-                &config,
-                &updates_tx,
-                &mut last_state,
-                &mut last_helper,
-                "periodic",
-            ),
+            Err(RecvTimeoutError::Timeout) => {
+                if scheduler.update_power_mode() {
+                    scheduler.replan_after_power_change();
+                }
+
+                let quiet = is_quiet(&scheduler.config.quiet_hours, scheduler.clock.now());
+                if scheduler.offline {
+                    // Probed again once the quiet hours are over.
+                    if !quiet {
+                        scheduler
+                            .run_when_online("connectivity-restored", &scheduler.enabled_sources());
+                    }
+                } else {
+                    let due = scheduler.due_sources(scheduler.clock.now());
+                    if !due.is_empty() {
+                        scheduler.run_when_online("scheduled", &due);
+                    }
+                }
+            }
             Err(RecvTimeoutError::Disconnected) => {
                 debug!("scheduler command channel disconnected");
                 break;
@@ -77,44 +277,961 @@ fn run_scheduler(
     }
 }
 
-fn run_once(
-    config: &EffectiveConfig,
-    updates_tx: &Sender<SchedulerUpdate>,
-    last_state: &mut AppState,
-    last_helper: &mut Option<DetectedAurHelper>,
-    trigger: &str,
-) {
-    let checking_state = last_state.clone().with_checking();
-    let _ = updates_tx.send(SchedulerUpdate {
-        state: checking_state,
-        snapshot: None,
-        helper: *last_helper,
-    });
+struct Scheduler {
+    config: EffectiveConfig,
+    updates_tx: Sender<SchedulerUpdate>,
+    last_state: AppState,
+    last_helper: Option<DetectedAurHelper>,
+    history: PendingHistory,
+    cache_path: PathBuf,
+    clock: Arc<dyn Clock>,
+    connectivity: ConnectivityProbe,
+    /// Set while checks are paused for lack of (unmetered) network.
+    offline: bool,
+    power: PowerMonitor,
+    /// Set while on a low battery or in power-saver mode: checks are spaced out and AUR
+    /// checks may be skipped.
+    power_saving: bool,
+    /// Official and AUR results merged; each source check replaces its own part, and local
+    /// recomputes start from it.
+    last_snapshot: Option<UpdateSnapshot>,
+    official: SourceState,
+    aur: SourceState,
+    /// Where check workers send `SchedulerCommand::SourceDone`.
+    results_tx: Sender<SchedulerCommand>,
+    /// Checks in flight; a source is never checked twice at the same time.
+    running: HashMap<UpdateSource, RunningCheck>,
+    /// Sources waiting out a fast-retry backoff; they still show as checking.
+    retrying: HashMap<UpdateSource, PendingRetry>,
+    /// Set when the local DB changed while an official check was in flight; the recompute
+    /// runs once it finishes instead of racing its sync on the checkupdates DB.
+    local_db_changed: bool,
+    next_run: u64,
+    config_error: Option<String>,
+}
+
+/// A source check running on a worker thread.
+struct RunningCheck {
+    id: u64,
+    /// Fast retries already used; 0 for the first try.
+    attempt: u32,
+    cancel: CancelToken,
+}
+
+/// A fast retry the loop starts once `at` has passed.
+struct PendingRetry {
+    attempt: u32,
+    at: DateTime<Local>,
+}
 
-    info!("running update check ({trigger})");
-    let checked_at = Local::now();
+/// Upper bound for a single wait. Scheduled times are wall-clock based, so the loop wakes
+/// up regularly instead of trusting one long monotonic timeout.
+const MAX_WAIT: Duration = Duration::from_secs(60);
 
-    match perform_check(config) {
-        Ok(outcome) => {
-            let state = AppState::from_snapshot(&outcome.snapshot, checked_at);
-            *last_state = state.clone();
-            *last_helper = outcome.helper;
+/// Wall-clock time gained on the monotonic clock between loop iterations that counts as a
+/// suspend/resume cycle when logind is unavailable.
+const SUSPEND_JUMP_THRESHOLD: Duration = Duration::from_secs(90);
 
-            let _ = updates_tx.send(SchedulerUpdate {
-                state,
-                snapshot: Some(outcome.snapshot),
-                helper: outcome.helper,
-            });
+/// Quiet period after the last local DB event before recomputing.
+const LOCAL_DB_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Quiet period after the last config file event before reloading, so a save that writes
+/// the file in several steps is read once.
+const CONFIG_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How long a snoozed update stays hidden, unless a newer version shows up first.
+const SNOOZE_DURATION: chrono::Duration = chrono::Duration::days(7);
+
+/// Where the kernel lists batteries and mains adapters.
+const POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+
+/// How often connectivity is re-probed while offline, so checks resume soon after it returns.
+const OFFLINE_POLL: Duration = Duration::from_secs(15);
+
+impl Scheduler {
+    fn enabled_sources(&self) -> Vec<UpdateSource> {
+        let mut sources = vec![UpdateSource::Official];
+        if self.config.enable_aur {
+            sources.push(UpdateSource::Aur);
+        }
+        sources
+    }
+
+    fn source(&self, source: UpdateSource) -> &SourceState {
+        match source {
+            UpdateSource::Official => &self.official,
+            UpdateSource::Aur => &self.aur,
+        }
+    }
+
+    fn source_mut(&mut self, source: UpdateSource) -> &mut SourceState {
+        match source {
+            UpdateSource::Official => &mut self.official,
+            UpdateSource::Aur => &mut self.aur,
+        }
+    }
+
+    fn wait_duration(&self, now: DateTime<Local>) -> Duration {
+        if self.offline {
+            return OFFLINE_POLL;
+        }
+
+        self.enabled_sources()
+            .into_iter()
+            .filter_map(|source| self.due_at(source))
+            .min()
+            .map(|next| (next - now).to_std().unwrap_or(Duration::ZERO))
+            .unwrap_or(MAX_WAIT)
+            .min(MAX_WAIT)
+    }
+
+    fn due_sources(&self, now: DateTime<Local>) -> Vec<UpdateSource> {
+        self.enabled_sources()
+            .into_iter()
+            .filter(|source| self.due_at(*source).is_some_and(|due| due <= now))
+            .collect()
+    }
+
+    /// When `source` should run next: its pending fast retry if any, else its scheduled
+    /// check. `None` while it is running.
+    fn due_at(&self, source: UpdateSource) -> Option<DateTime<Local>> {
+        if self.running.contains_key(&source) {
+            return None;
+        }
+        match self.retrying.get(&source) {
+            Some(retry) => Some(retry.at),
+            None => self.source(source).next_check,
+        }
+    }
+
+    /// Re-reads the power state; returns true when power saving was switched on or off.
+    /// power-profiles-daemon is only asked over D-Bus while power saving is enabled.
+    fn update_power_mode(&mut self) -> bool {
+        let state = self.power.state();
+        let power_saver = self.config.power.enabled() && self.power.power_saver_active();
+        let saving = self.config.power.should_save(state, power_saver);
+        if saving == self.power_saving {
+            return false;
         }
-        Err(err) => {
-            warn!("update check failed: {err}");
-            let state = last_state.clone().with_error(err.to_string(), checked_at);
-            *last_state = state.clone();
-            let _ = updates_tx.send(SchedulerUpdate {
-                state,
-                snapshot: None,
-                helper: *last_helper,
+
+        info!(
+            "power saving {} (on battery: {}, capacity: {:?}, power-saver: {power_saver})",
+            if saving { "enabled" } else { "disabled" },
+            state.on_battery,
+            state.capacity
+        );
+        self.power_saving = saving;
+        true
+    }
+
+    /// Recomputes each source's next check from its last one, so plugging in AC runs an
+    /// overdue check right away instead of waiting out the stretched interval.
+    fn replan_after_power_change(&mut self) {
+        for source in self.enabled_sources() {
+            let state = self.source(source);
+            if let (Some(last_checked), Some(_)) = (state.last_checked, state.next_check) {
+                self.plan_next_check(source, last_checked);
+            }
+        }
+        self.publish(None);
+    }
+
+    fn plan_next_check(&mut self, source: UpdateSource, now: DateTime<Local>) {
+        let schedule = self.config.schedule_for(source);
+        // In power-saving mode, skip all but every `poll_factor`-th scheduled run.
+        let runs = if self.power_saving {
+            self.config.power.poll_factor.max(1)
+        } else {
+            1
+        };
+        let scheduled = (0..runs).try_fold(now, |after, _| {
+            next_check(schedule, &self.config.quiet_hours, after)
+        });
+        let failures = self.source(source).consecutive_failures;
+
+        // While checks keep failing, retry on a growing degraded delay, but never later than
+        // the regular schedule would, and like it, not during quiet hours.
+        let degraded = (failures > 0)
+            .then(|| chrono::Duration::from_std(self.config.retry.degraded_delay(failures)).ok())
+            .flatten()
+            .and_then(|delay| after_quiet_hours(&self.config.quiet_hours, now + delay));
+
+        let next = match (scheduled, degraded) {
+            (Some(scheduled), Some(degraded)) => Some(scheduled.min(degraded)),
+            (scheduled, degraded) => scheduled.or(degraded),
+        };
+        match next {
+            Some(next) => debug!("next {} check at {next}", source.label()),
+            None => warn!("schedule {schedule} has no upcoming run"),
+        }
+        self.source_mut(source).next_check = next;
+    }
+
+    /// Emits the snapshot cached by a previous session, marked stale, so the tray shows
+    /// counts right away instead of waiting for the first sync.
+    fn restore_cached(&mut self) {
+        let cached = match load_cached_check(&self.cache_path) {
+            Ok(Some(cached)) => cached,
+            Ok(None) => return,
+            Err(err) => {
+                warn!(
+                    "ignoring cached check at {}: {err}",
+                    self.cache_path.display()
+                );
+                return;
+            }
+        };
+
+        info!("restored cached check from {}", self.cache_path.display());
+        let state = cached.state.with_stale();
+        self.official = state.official.clone();
+        self.aur = state.aur.clone().unwrap_or_default();
+        self.last_state = state;
+        self.last_helper = cached.helper;
+        self.last_snapshot = Some(cached.snapshot.clone());
+
+        self.send_update(Some(cached.snapshot), None);
+    }
+
+    fn store_cached(&self, snapshot: &UpdateSnapshot) {
+        let cached = CachedCheck {
+            helper: self.last_helper,
+            state: self.last_state.clone(),
+            snapshot: snapshot.clone(),
+        };
+
+        if let Err(err) = save_cached_check(&self.cache_path, &cached) {
+            warn!("failed to save cached check: {err}");
+        }
+    }
+
+    /// Rebuilds the tray state from the merged snapshot and the per-source states, and sends
+    /// it along with `snapshot` when the counts changed.
+    fn publish(&mut self, snapshot: Option<UpdateSnapshot>) {
+        self.rebuild_state();
+        if let Some(snapshot) = &snapshot {
+            self.store_cached(snapshot);
+        }
+        self.send_update(snapshot, None);
+    }
+
+    fn rebuild_state(&mut self) {
+        let now = self.clock.now();
+        let merged = self.last_snapshot.clone().unwrap_or_default();
+        let aur = self.config.enable_aur.then(|| self.aur.clone());
+        let mut state = AppState::from_sources(&merged, self.official.clone(), aur)
+            .with_pending_threshold(self.config.pending_warn_days, now);
+        state.stale = self.last_state.stale;
+        if self.offline {
+            state = state.with_offline(self.last_state.metered);
+        }
+        self.last_state = state;
+    }
+
+    fn send_update(&self, snapshot: Option<UpdateSnapshot>, config: Option<EffectiveConfig>) {
+        let _ = self.updates_tx.send(SchedulerUpdate {
+            state: self.last_state.clone(),
+            snapshot,
+            helper: self.last_helper,
+            config,
+            config_error: self.config_error.clone(),
+        });
+    }
+
+    /// Switches to a reloaded config and replans both sources from their last check. A
+    /// config that failed to load is reported to the tray and the current one kept.
+    fn reconfigure(&mut self, result: Result<Box<EffectiveConfig>, String>) {
+        let config = match result {
+            Ok(config) => *config,
+            Err(err) => {
+                warn!("keeping the current config: {err}");
+                self.config_error = Some(err);
+                self.send_update(None, None);
+                return;
+            }
+        };
+
+        info!("config reloaded");
+        self.config_error = None;
+        self.connectivity = ConnectivityProbe::new(
+            PathBuf::from("/proc"),
+            config.offline_detection,
+            config.skip_when_metered,
+        );
+        let aur_disabled = self.config.enable_aur && !config.enable_aur;
+        self.config = config;
+
+        if aur_disabled {
+            if let Some(check) = self.running.remove(&UpdateSource::Aur) {
+                check.cancel.cancel();
+            }
+            self.retrying.remove(&UpdateSource::Aur);
+            self.aur = SourceState::default();
+            if let Some(snapshot) = self.last_snapshot.as_mut() {
+                snapshot.aur.clear();
+            }
+        }
+
+        self.update_power_mode();
+        let now = self.clock.now();
+        for source in self.enabled_sources() {
+            match self.source(source).last_checked {
+                Some(last_checked) => self.plan_next_check(source, last_checked),
+                // E.g. AUR checks were just enabled: run one on the next loop iteration.
+                None => self.source_mut(source).next_check = Some(now),
+            }
+        }
+
+        self.rebuild_state();
+        self.send_update(None, Some(self.config.clone()));
+    }
+
+    /// Runs checks unless the network is down (or metered and configured to skip); in that
+    /// case the tray shows `Status::Offline` and the loop polls until connectivity returns.
+    /// During quiet hours the checks are deferred to the end of the window instead. Manual
+    /// refreshes bypass this and always run.
+    fn run_when_online(&mut self, trigger: &str, sources: &[UpdateSource]) {
+        let now = self.clock.now();
+        if is_quiet(&self.config.quiet_hours, now) {
+            self.defer_past_quiet_hours(trigger, sources, now);
+            return;
+        }
+
+        let connectivity = self.connectivity.probe();
+        if connectivity == Connectivity::Online {
+            if self.offline {
+                info!("connectivity restored");
+            }
+            self.offline = false;
+            self.run_sources(trigger, sources);
+            return;
+        }
+
+        if !self.offline {
+            info!("skipping {trigger} check: {connectivity:?}");
+            self.offline = true;
+            self.last_state = self
+                .last_state
+                .clone()
+                .with_offline(connectivity == Connectivity::Metered);
+            self.send_update(None, None);
+        }
+    }
+
+    fn defer_past_quiet_hours(
+        &mut self,
+        trigger: &str,
+        sources: &[UpdateSource],
+        now: DateTime<Local>,
+    ) {
+        let Some(end) = after_quiet_hours(&self.config.quiet_hours, now) else {
+            return;
+        };
+        info!("in quiet hours, deferring {trigger} check to {end}");
+        for source in sources {
+            self.source_mut(*source).next_check = Some(end);
+        }
+        self.publish(None);
+    }
+
+    /// Starts a check of each of `sources` on its own worker thread. Results come back as
+    /// `SchedulerCommand::SourceDone`, so each source is published as soon as it finishes and
+    /// the loop stays free for other sources and commands meanwhile.
+    fn run_sources(&mut self, trigger: &str, sources: &[UpdateSource]) {
+        let skip_aur = self.power_saving && self.config.power.skip_aur;
+        let mut sources = sources.to_vec();
+        if skip_aur && sources.contains(&UpdateSource::Aur) {
+            info!("skipping AUR check to save power");
+            sources.retain(|source| *source != UpdateSource::Aur);
+            if self.last_helper.is_none() {
+                self.last_helper =
+                    detect_aur_helper(self.config.aur_helper, self.config.enable_aur);
+            }
+            self.plan_next_check(UpdateSource::Aur, self.clock.now());
+        }
+
+        let config = Arc::new(self.config.clone());
+        for source in sources {
+            if self.running.contains_key(&source) {
+                debug!("{} check already running", source.label());
+                continue;
+            }
+            let attempt = self
+                .retrying
+                .remove(&source)
+                .map_or(0, |retry| retry.attempt);
+            let limits = RunLimits::new(self.config.command_timeout);
+            self.next_run += 1;
+            self.running.insert(
+                source,
+                RunningCheck {
+                    id: self.next_run,
+                    attempt,
+                    cancel: limits.cancel.clone(),
+                },
+            );
+            self.source_mut(source).checking = true;
+
+            info!("running {} update check ({trigger})", source.label());
+            let (config, results_tx, run) =
+                (Arc::clone(&config), self.results_tx.clone(), self.next_run);
+            thread::spawn(move || {
+                let result = check_source(&config, source, &limits);
+                let _ = results_tx.send(SchedulerCommand::SourceDone {
+                    source,
+                    run,
+                    result,
+                });
             });
         }
+        self.publish(None);
+    }
+
+    fn finish_source(
+        &mut self,
+        source: UpdateSource,
+        run: u64,
+        result: Result<SourceCheck, CommandError>,
+    ) {
+        if self
+            .running
+            .get(&source)
+            .is_none_or(|check| check.id != run)
+        {
+            debug!("ignoring result of a cancelled {} check", source.label());
+            return;
+        }
+        let Some(check) = self.running.remove(&source) else {
+            return;
+        };
+        if let Err(err) = &result
+            && self.schedule_retry(source, check.attempt, err)
+        {
+            return;
+        }
+        self.apply_source_result(source, result);
+        if source == UpdateSource::Official && self.local_db_changed {
+            self.local_db_changed = false;
+            self.recompute_local();
+        }
+    }
+
+    /// Queues a fast retry after a failed try, as long as the source was healthy before and
+    /// has retries left. Once a source is failing, each attempt is a single try; the degraded
+    /// delay in `plan_next_check` spaces them out instead of hammering a mirror that is down.
+    fn schedule_retry(&mut self, source: UpdateSource, attempt: u32, err: &CommandError) -> bool {
+        if err.is_cancelled()
+            || self.source(source).consecutive_failures > 0
+            || attempt >= self.config.retry.fast_retries
+        {
+            return false;
+        }
+
+        let now = self.clock.now();
+        let delay = self.config.retry.backoff_delay(attempt, jitter_sample(now));
+        let backoff = now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX);
+        let at = after_quiet_hours(&self.config.quiet_hours, backoff).unwrap_or(backoff);
+        warn!(
+            "{} check attempt {} failed ({err}); retrying at {}",
+            source.label(),
+            attempt + 1,
+            at.format("%H:%M:%S")
+        );
+        self.retrying.insert(
+            source,
+            PendingRetry {
+                attempt: attempt + 1,
+                at,
+            },
+        );
+        true
+    }
+
+    /// Kills the running checks and shows their sources as idle right away; the workers'
+    /// late results are ignored.
+    fn cancel(&mut self) {
+        if self.running.is_empty() && self.retrying.is_empty() {
+            debug!("no check to cancel");
+            return;
+        }
+        info!("cancelling running checks");
+        for check in self.running.values() {
+            check.cancel.cancel();
+        }
+        let sources: Vec<UpdateSource> = mem::take(&mut self.running)
+            .into_keys()
+            .chain(mem::take(&mut self.retrying).into_keys())
+            .collect();
+        for source in sources {
+            let command = format!("{} check", source.label());
+            self.apply_source_result(source, Err(CommandError::Cancelled { command }));
+        }
+    }
+
+    fn apply_source_result(
+        &mut self,
+        source: UpdateSource,
+        result: Result<SourceCheck, CommandError>,
+    ) {
+        let checked_at = self.clock.now();
+        let check = match result {
+            Ok(check) => check,
+            Err(err) if err.is_cancelled() => {
+                info!("{} update check cancelled", source.label());
+                self.source_mut(source).checking = false;
+                self.plan_next_check(source, checked_at);
+                self.publish(None);
+                return;
+            }
+            Err(err) => {
+                self.source_mut(source).failed(err.to_string(), checked_at);
+                warn!(
+                    "{} update check failed ({} consecutive failures): {err}",
+                    source.label(),
+                    self.source(source).consecutive_failures
+                );
+                self.plan_next_check(source, checked_at);
+                self.publish(None);
+                return;
+            }
+        };
+
+        let mut snapshot = self.last_snapshot.clone().unwrap_or_default();
+        snapshot.snoozed.retain(|update| update.source != source);
+        match source {
+            UpdateSource::Official => {
+                snapshot.official = check.updates;
+                snapshot.rebuilds = check.rebuilds;
+            }
+            UpdateSource::Aur => {
+                snapshot.aur = check.updates;
+                self.last_helper = check.helper;
+            }
+        }
+
+        self.history.record(&mut snapshot, checked_at, &[source]);
+        if let Err(err) = self.history.save() {
+            warn!("failed to save pending history: {err}");
+        }
+
+        self.source_mut(source).succeeded(checked_at);
+        self.plan_next_check(source, checked_at);
+        self.last_state.stale = false;
+        self.last_snapshot = Some(snapshot.clone());
+        self.publish(Some(snapshot));
+    }
+
+    fn snooze(&mut self, source: UpdateSource, name: &str, latest: &str) {
+        let now = self.clock.now();
+        info!("snoozing {name} {latest}");
+        self.history
+            .snooze(source, name, latest, now + SNOOZE_DURATION);
+
+        // Saved even before the first check, so the snooze outlives a restart.
+        let snapshot = self.last_snapshot.clone().map(|mut snapshot| {
+            self.history.record(&mut snapshot, now, &[]);
+            snapshot
+        });
+        if let Err(err) = self.history.save() {
+            warn!("failed to save pending history: {err}");
+        }
+        let Some(snapshot) = snapshot else {
+            return;
+        };
+
+        self.last_snapshot = Some(snapshot.clone());
+        self.publish(Some(snapshot));
+    }
+
+    /// Refreshes counts after packages changed locally, reusing the last sync. Skipped while
+    /// the tray shows a check in progress, an error, or offline, so those states stay put,
+    /// and deferred while an official check is using the checkupdates DB.
+    fn recompute_local(&mut self) {
+        if !self.config.watch_local_db {
+            debug!("ignoring local DB change, watch_local_db is off");
+            return;
+        }
+        if self.running.contains_key(&UpdateSource::Official)
+            || self.retrying.contains_key(&UpdateSource::Official)
+        {
+            debug!("local DB changed during an official check, recomputing once it finishes");
+            self.local_db_changed = true;
+            return;
+        }
+
+        let current = matches!(
+            self.last_state.status,
+            Status::UpToDate | Status::UpdatesAvailable | Status::Overdue
+        );
+        let Some(previous) = self.last_snapshot.as_ref() else {
+            return;
+        };
+        if !current {
+            debug!(
+                "ignoring local DB change in {:?} state",
+                self.last_state.status
+            );
+            return;
+        }
+
+        info!("local package database changed, recomputing pending updates");
+        let mut snapshot = match recompute_local(&self.config, previous) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                warn!("failed to recompute pending updates: {err}");
+                return;
+            }
+        };
+
+        // Only sources that completed a check have a list to recompute from.
+        let known: Vec<UpdateSource> = self
+            .enabled_sources()
+            .into_iter()
+            .filter(|source| self.source(*source).last_success.is_some())
+            .collect();
+        self.history.record(&mut snapshot, self.clock.now(), &known);
+        if let Err(err) = self.history.save() {
+            warn!("failed to save pending history: {err}");
+        }
+
+        self.last_snapshot = Some(snapshot.clone());
+        self.publish(Some(snapshot));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::schedule::Schedule;
+    use crate::state::{PackageUpdate, SourceStatus};
+
+    struct FakeClock(Mutex<DateTime<Local>>);
+
+    impl FakeClock {
+        fn advance(&self, by: chrono::Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Local> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn scheduler(dir: &std::path::Path) -> (Scheduler, Receiver<SchedulerUpdate>) {
+        scheduler_with_clock(dir, Arc::new(SystemClock))
+    }
+
+    fn scheduler_with_clock(
+        dir: &std::path::Path,
+        clock: Arc<dyn Clock>,
+    ) -> (Scheduler, Receiver<SchedulerUpdate>) {
+        let mut config = EffectiveConfig::default();
+        config.retry.jitter = 0.0;
+        let (updates_tx, updates_rx) = mpsc::channel();
+        let (results_tx, _) = mpsc::channel();
+        let scheduler = Scheduler {
+            connectivity: ConnectivityProbe::new(dir.join("proc"), false, false),
+            config,
+            updates_tx,
+            last_state: AppState::default(),
+            last_helper: None,
+            history: PendingHistory::load(dir.join("pending.toml")),
+            cache_path: dir.join("last-check.json"),
+            clock,
+            offline: false,
+            power: PowerMonitor::new(dir.join("power_supply")),
+            power_saving: false,
+            last_snapshot: None,
+            official: SourceState::default(),
+            aur: SourceState::default(),
+            results_tx,
+            running: HashMap::new(),
+            retrying: HashMap::new(),
+            local_db_changed: false,
+            next_run: 0,
+            config_error: None,
+        };
+        (scheduler, updates_rx)
+    }
+
+    fn running(scheduler: &mut Scheduler, source: UpdateSource, id: u64) -> CancelToken {
+        let cancel = CancelToken::default();
+        scheduler.running.insert(
+            source,
+            RunningCheck {
+                id,
+                attempt: 0,
+                cancel: cancel.clone(),
+            },
+        );
+        scheduler.source_mut(source).checking = true;
+        cancel
+    }
+
+    fn timed_out() -> Result<SourceCheck, CommandError> {
+        Err(CommandError::Timeout {
+            command: "checkupdates".to_string(),
+            seconds: 120,
+        })
+    }
+
+    fn no_updates() -> Result<SourceCheck, CommandError> {
+        Ok(SourceCheck {
+            updates: Vec::new(),
+            rebuilds: Vec::new(),
+            helper: None,
+        })
+    }
+
+    #[test]
+    fn sources_finish_independently_and_stale_results_are_ignored() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let (mut scheduler, updates) = scheduler(temp.path());
+        running(&mut scheduler, UpdateSource::Official, 1);
+        running(&mut scheduler, UpdateSource::Aur, 2);
+
+        scheduler.finish_source(UpdateSource::Official, 7, no_updates());
+        assert!(scheduler.official.checking);
+
+        scheduler.finish_source(UpdateSource::Official, 1, no_updates());
+        assert!(!scheduler.official.checking);
+        assert!(scheduler.official.last_success.is_some());
+        assert!(scheduler.aur.checking);
+        assert!(scheduler.due_sources(scheduler.clock.now()).is_empty());
+
+        let update = updates.try_recv().expect("published");
+        assert!(update.snapshot.is_some());
+        assert!(matches!(
+            update.state.official.status(),
+            SourceStatus::Ok(_)
+        ));
+    }
+
+    #[test]
+    fn cancel_stops_every_running_check_at_once() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let (mut scheduler, _updates) = scheduler(temp.path());
+        let official = running(&mut scheduler, UpdateSource::Official, 1);
+        let aur = running(&mut scheduler, UpdateSource::Aur, 2);
+
+        scheduler.cancel();
+        assert!(official.is_cancelled() && aur.is_cancelled());
+        assert!(!scheduler.official.checking && !scheduler.aur.checking);
+        assert!(scheduler.official.next_check.is_some());
+
+        // The worker reports back later; its result no longer applies.
+        scheduler.finish_source(UpdateSource::Aur, 2, no_updates());
+        assert!(scheduler.aur.last_success.is_none());
+    }
+
+    #[test]
+    fn failed_tries_are_retried_from_the_loop_with_backoff() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let clock = Arc::new(FakeClock(Mutex::new(Local::now())));
+        let (mut scheduler, _updates) = scheduler_with_clock(temp.path(), clock.clone());
+        scheduler.official.next_check = Some(clock.now() + chrono::Duration::hours(1));
+        running(&mut scheduler, UpdateSource::Official, 1);
+
+        scheduler.finish_source(UpdateSource::Official, 1, timed_out());
+        assert!(scheduler.official.checking);
+        assert_eq!(scheduler.official.consecutive_failures, 0);
+        assert_eq!(
+            scheduler.wait_duration(clock.now()),
+            Duration::from_secs(10)
+        );
+        assert!(scheduler.due_sources(clock.now()).is_empty());
+
+        clock.advance(chrono::Duration::seconds(10));
+        assert_eq!(
+            scheduler.due_sources(clock.now()),
+            vec![UpdateSource::Official]
+        );
+
+        // The last fast retry failing counts as one failed check.
+        scheduler.retrying.clear();
+        scheduler.running.insert(
+            UpdateSource::Official,
+            RunningCheck {
+                id: 2,
+                attempt: scheduler.config.retry.fast_retries,
+                cancel: CancelToken::default(),
+            },
+        );
+        scheduler.finish_source(UpdateSource::Official, 2, timed_out());
+        assert!(!scheduler.official.checking);
+        assert_eq!(scheduler.official.consecutive_failures, 1);
+        assert!(scheduler.retrying.is_empty());
+    }
+
+    #[test]
+    fn failing_sources_are_rechecked_on_the_degraded_delay() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let clock = Arc::new(FakeClock(Mutex::new(Local::now())));
+        let (mut scheduler, _updates) = scheduler_with_clock(temp.path(), clock.clone());
+        scheduler.config.schedule = Schedule::every_minutes(30);
+        scheduler.official.consecutive_failures = 1;
+
+        let mut delays = Vec::new();
+        for id in 1..=4 {
+            running(&mut scheduler, UpdateSource::Official, id);
+            scheduler.finish_source(UpdateSource::Official, id, timed_out());
+            let next = scheduler.official.next_check.expect("planned");
+            delays.push((next - clock.now()).num_minutes());
+            clock.advance(next - clock.now());
+        }
+
+        // Failures 2, 3, 4 and 5 wait 10, 20, 40 minutes, capped by the 30-minute schedule.
+        assert_eq!(delays, vec![10, 20, 30, 30]);
+        assert!(scheduler.retrying.is_empty());
+    }
+
+    #[test]
+    fn low_battery_under_the_sysfs_root_stretches_checks_only_when_enabled() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let battery = temp.path().join("power_supply").join("BAT0");
+        fs::create_dir_all(&battery).expect("create supply dir");
+        for (attr, value) in [
+            ("type", "Battery"),
+            ("status", "Discharging"),
+            ("capacity", "20"),
+        ] {
+            fs::write(battery.join(attr), value).expect("write attr");
+        }
+        let clock = Arc::new(FakeClock(Mutex::new(Local::now())));
+        let (mut scheduler, _updates) = scheduler_with_clock(temp.path(), clock.clone());
+        scheduler.config.schedule = Schedule::every_minutes(30);
+
+        assert!(!scheduler.update_power_mode(), "off by default");
+
+        scheduler.config.power.threshold = 50;
+        assert!(scheduler.update_power_mode());
+        scheduler.plan_next_check(UpdateSource::Official, clock.now());
+        let next = scheduler.official.next_check.expect("planned");
+        assert_eq!((next - clock.now()).num_minutes(), 90);
+    }
+
+    #[test]
+    fn config_written_into_a_missing_directory_is_picked_up() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("pactrack").join("config.toml");
+        let (tx, rx) = mpsc::channel();
+        watch_config(
+            ConfigSource {
+                path: path.clone(),
+                overrides: Default::default(),
+            },
+            tx,
+        )
+        .expect("watch config");
+
+        fs::write(&path, "poll_minutes = 5\n").expect("write config");
+        match rx.recv_timeout(Duration::from_secs(3)) {
+            Ok(SchedulerCommand::Reconfigure(Ok(config))) => {
+                assert_eq!(config.poll_minutes, 5)
+            }
+            other => panic!("expected a reload, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn snooze_before_the_first_check_survives_the_other_source() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let (mut scheduler, updates) = scheduler(temp.path());
+
+        scheduler.snooze(UpdateSource::Aur, "foo-git", "r2-1");
+        assert!(updates.try_recv().is_err());
+
+        running(&mut scheduler, UpdateSource::Official, 1);
+        scheduler.finish_source(UpdateSource::Official, 1, no_updates());
+
+        // Saved, and still there after a restart.
+        scheduler.history = PendingHistory::load(temp.path().join("pending.toml"));
+        running(&mut scheduler, UpdateSource::Aur, 2);
+        let foo = PackageUpdate::new("foo-git", "r1-1", "r2-1", UpdateSource::Aur);
+        scheduler.finish_source(
+            UpdateSource::Aur,
+            2,
+            Ok(SourceCheck {
+                updates: vec![foo],
+                rebuilds: Vec::new(),
+                helper: None,
+            }),
+        );
+        let snapshot = scheduler.last_snapshot.as_ref().expect("snapshot");
+        assert!(snapshot.aur.is_empty());
+        assert_eq!(snapshot.snoozed[0].name, "foo-git");
+    }
+
+    #[test]
+    fn retries_and_degraded_checks_wait_out_quiet_hours() {
+        use chrono::TimeZone;
+
+        let temp = tempfile::tempdir().expect("tempdir");
+        let evening = Local.with_ymd_and_hms(2024, 3, 5, 21, 59, 55).unwrap();
+        let morning = Local.with_ymd_and_hms(2024, 3, 6, 7, 0, 0).unwrap();
+        let clock = Arc::new(FakeClock(Mutex::new(evening)));
+        let (mut scheduler, _updates) = scheduler_with_clock(temp.path(), clock.clone());
+        scheduler.config.quiet_hours = vec!["22:00-07:00".parse().expect("window")];
+
+        running(&mut scheduler, UpdateSource::Official, 1);
+        scheduler.finish_source(UpdateSource::Official, 1, timed_out());
+        assert_eq!(scheduler.retrying[&UpdateSource::Official].at, morning);
+
+        scheduler.retrying.clear();
+        scheduler.official.consecutive_failures = 1;
+        running(&mut scheduler, UpdateSource::Official, 2);
+        scheduler.finish_source(UpdateSource::Official, 2, timed_out());
+        assert_eq!(scheduler.official.next_check, Some(morning));
+    }
+
+    #[test]
+    fn resume_during_quiet_hours_checks_when_they_end() {
+        use chrono::TimeZone;
+
+        let temp = tempfile::tempdir().expect("tempdir");
+        let night = Local.with_ymd_and_hms(2024, 3, 6, 3, 0, 0).unwrap();
+        let morning = Local.with_ymd_and_hms(2024, 3, 6, 7, 0, 0).unwrap();
+        let clock = Arc::new(FakeClock(Mutex::new(night)));
+        let (mut scheduler, _updates) = scheduler_with_clock(temp.path(), clock.clone());
+        scheduler.config.quiet_hours = vec!["22:00-07:00".parse().expect("window")];
+        scheduler.official.next_check = Some(morning + chrono::Duration::hours(5));
+
+        scheduler.run_when_online("resume", &[UpdateSource::Official]);
+        assert!(scheduler.running.is_empty());
+        assert_eq!(scheduler.official.next_check, Some(morning));
+
+        clock.advance(morning - night);
+        assert_eq!(
+            scheduler.due_sources(clock.now()),
+            vec![UpdateSource::Official]
+        );
+    }
+
+    #[test]
+    fn local_db_changes_wait_for_the_official_check_and_respect_reloads() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let (mut scheduler, updates) = scheduler(temp.path());
+        scheduler.last_snapshot = Some(UpdateSnapshot::default());
+        scheduler.last_state.status = Status::UpToDate;
+        running(&mut scheduler, UpdateSource::Official, 1);
+
+        scheduler.recompute_local();
+        assert!(scheduler.local_db_changed);
+        assert!(updates.try_recv().is_err());
+
+        // A failing source is not retried fast, so the check finishes with an error and the
+        // deferred recompute is dropped along with the stale state.
+        scheduler.official.consecutive_failures = 1;
+        scheduler.finish_source(UpdateSource::Official, 1, timed_out());
+        assert!(!scheduler.local_db_changed);
+
+        scheduler.config.watch_local_db = false;
+        running(&mut scheduler, UpdateSource::Official, 2);
+        scheduler.recompute_local();
+        assert!(!scheduler.local_db_changed);
     }
 }
@@ -0,0 +1,743 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use toml_edit::{Array, Document, Item, Table, Value};
+
+use crate::config::{ConfigError, parse_config};
+
+/// Tabs of the preferences dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Schedule,
+    Conditions,
+    Notifications,
+    Commands,
+    Tray,
+}
+
+impl Page {
+    pub const ALL: [Page; 5] = [
+        Page::Schedule,
+        Page::Conditions,
+        Page::Notifications,
+        Page::Commands,
+        Page::Tray,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Schedule => "Schedule",
+            Self::Conditions => "Network & power",
+            Self::Notifications => "Notifications",
+            Self::Commands => "Commands",
+            Self::Tray => "Tray",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum FieldKind {
+    Bool {
+        default: bool,
+    },
+    Integer {
+        min: i64,
+        max: i64,
+        default: i64,
+    },
+    /// Left out of the file while 0.
+    OptionalInteger {
+        max: i64,
+    },
+    Choice {
+        options: &'static [&'static str],
+        default: &'static str,
+    },
+    Text {
+        default: &'static str,
+    },
+    /// String array, edited as comma-separated text.
+    List {
+        default: &'static [&'static str],
+    },
+    /// Cron expression or comma-separated daily times; left out of the file while empty.
+    Schedule,
+    /// `[icons]` entry with optional light and dark variants.
+    Icon,
+}
+
+/// One config file key the preferences dialog edits. Keys inside a table are written as
+/// `table.key`.
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub page: Page,
+    pub kind: FieldKind,
+}
+
+impl Field {
+    fn location(&self) -> (Option<&'static str>, &'static str) {
+        match self.key.split_once('.') {
+            Some((table, key)) => (Some(table), key),
+            None => (None, self.key),
+        }
+    }
+
+    /// What the key means while it is missing from the file.
+    pub fn default_value(&self) -> FieldValue {
+        match self.kind {
+            FieldKind::Bool { default } => FieldValue::Bool(default),
+            FieldKind::Integer { default, .. } => FieldValue::Integer(default),
+            FieldKind::OptionalInteger { .. } => FieldValue::Integer(0),
+            FieldKind::Choice { default, .. } | FieldKind::Text { default } => {
+                FieldValue::Text(default.to_string())
+            }
+            FieldKind::List { default } => FieldValue::Text(default.join(", ")),
+            FieldKind::Schedule => FieldValue::Text(String::new()),
+            FieldKind::Icon => FieldValue::Icon {
+                light: String::new(),
+                dark: String::new(),
+            },
+        }
+    }
+
+    /// Allowed values of an integer field.
+    pub fn range(&self) -> Option<(i64, i64)> {
+        match self.kind {
+            FieldKind::Integer { min, max, .. } => Some((min, max)),
+            FieldKind::OptionalInteger { max } => Some((0, max)),
+            _ => None,
+        }
+    }
+
+    fn check(&self, value: &FieldValue) -> Result<(), SettingsError> {
+        if let FieldKind::Choice { options, .. } = self.kind {
+            return match value {
+                FieldValue::Text(choice) if options.contains(&choice.as_str()) => Ok(()),
+                _ => Err(SettingsError::UnknownChoice {
+                    label: self.label,
+                    options,
+                }),
+            };
+        }
+        let Some((min, max)) = self.range() else {
+            return Ok(());
+        };
+        match value {
+            FieldValue::Integer(v) if (min..=max).contains(v) => Ok(()),
+            _ => Err(SettingsError::OutOfRange {
+                label: self.label,
+                min,
+                max,
+            }),
+        }
+    }
+}
+
+/// A field's value as the dialog shows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldValue {
+    Bool(bool),
+    Integer(i64),
+    Text(String),
+    /// An empty `dark` uses `light` for both.
+    Icon {
+        light: String,
+        dark: String,
+    },
+}
+
+const SCHEDULE: Page = Page::Schedule;
+const CONDITIONS: Page = Page::Conditions;
+const NOTIFICATIONS: Page = Page::Notifications;
+const COMMANDS: Page = Page::Commands;
+const TRAY: Page = Page::Tray;
+
+const fn icon(key: &'static str, label: &'static str) -> Field {
+    Field {
+        key,
+        label,
+        page: TRAY,
+        kind: FieldKind::Icon,
+    }
+}
+
+/// Every config file key, in dialog order.
+pub const FIELDS: &[Field] = &[
+    Field {
+        key: "poll_minutes",
+        label: "Check every (minutes)",
+        page: SCHEDULE,
+        kind: FieldKind::Integer {
+            min: 1,
+            max: 10080,
+            default: 30,
+        },
+    },
+    Field {
+        key: "schedule",
+        label: "Schedule instead (cron or HH:MM, HH:MM)",
+        page: SCHEDULE,
+        kind: FieldKind::Schedule,
+    },
+    Field {
+        key: "aur_poll_minutes",
+        label: "Check AUR every (minutes, 0 = with official)",
+        page: SCHEDULE,
+        kind: FieldKind::OptionalInteger { max: 10080 },
+    },
+    Field {
+        key: "aur_schedule",
+        label: "AUR schedule (cron or HH:MM, HH:MM)",
+        page: SCHEDULE,
+        kind: FieldKind::Schedule,
+    },
+    Field {
+        key: "quiet_hours",
+        label: "Quiet hours (HH:MM-HH:MM, …)",
+        page: SCHEDULE,
+        kind: FieldKind::List { default: &[] },
+    },
+    Field {
+        key: "retry_attempts",
+        label: "Retries after a failed check",
+        page: SCHEDULE,
+        kind: FieldKind::Integer {
+            min: 0,
+            max: 20,
+            default: 3,
+        },
+    },
+    Field {
+        key: "retry_base_seconds",
+        label: "First retry after (seconds)",
+        page: SCHEDULE,
+        kind: FieldKind::Integer {
+            min: 1,
+            max: 3600,
+            default: 10,
+        },
+    },
+    Field {
+        key: "retry_degraded_minutes",
+        label: "Then retry every (minutes)",
+        page: SCHEDULE,
+        kind: FieldKind::Integer {
+            min: 1,
+            max: 1440,
+            default: 5,
+        },
+    },
+    Field {
+        key: "command_timeout_seconds",
+        label: "Command timeout (seconds)",
+        page: SCHEDULE,
+        kind: FieldKind::Integer {
+            min: 1,
+            max: 86400,
+            default: 300,
+        },
+    },
+    Field {
+        key: "offline_detection",
+        label: "Skip checks while offline",
+        page: CONDITIONS,
+        kind: FieldKind::Bool { default: true },
+    },
+    Field {
+        key: "skip_when_metered",
+        label: "Skip checks on metered connections",
+        page: CONDITIONS,
+        kind: FieldKind::Bool { default: false },
+    },
+    Field {
+        key: "battery_threshold",
+        label: "Save power below battery (%, 0 = never)",
+        page: CONDITIONS,
+        kind: FieldKind::Integer {
+            min: 0,
+            max: 100,
+            default: 0,
+        },
+    },
+    Field {
+        key: "battery_poll_factor",
+        label: "On low battery, run every n-th check",
+        page: CONDITIONS,
+        kind: FieldKind::Integer {
+            min: 1,
+            max: 100,
+            default: 3,
+        },
+    },
+    Field {
+        key: "battery_skip_aur",
+        label: "Skip AUR checks on low battery",
+        page: CONDITIONS,
+        kind: FieldKind::Bool { default: true },
+    },
+    Field {
+        key: "watch_local_db",
+        label: "Watch the local package database",
+        page: CONDITIONS,
+        kind: FieldKind::Bool { default: true },
+    },
+    Field {
+        key: "notify_on_change",
+        label: "Notify about new updates",
+        page: NOTIFICATIONS,
+        kind: FieldKind::Bool { default: true },
+    },
+    Field {
+        key: "notify_on_decrease",
+        label: "Notify when updates go away",
+        page: NOTIFICATIONS,
+        kind: FieldKind::Bool { default: false },
+    },
+    Field {
+        key: "pending_warn_days",
+        label: "Warn after pending for (days, 0 = never)",
+        page: NOTIFICATIONS,
+        kind: FieldKind::Integer {
+            min: 0,
+            max: 365,
+            default: 0,
+        },
+    },
+    Field {
+        key: "rebuild_watch",
+        label: "Packages to check for rebuilds",
+        page: NOTIFICATIONS,
+        kind: FieldKind::List {
+            default: &crate::config::DEFAULT_REBUILD_WATCH,
+        },
+    },
+    Field {
+        key: "enable_aur",
+        label: "Check the AUR",
+        page: COMMANDS,
+        kind: FieldKind::Bool { default: true },
+    },
+    Field {
+        key: "aur_helper",
+        label: "AUR helper",
+        page: COMMANDS,
+        kind: FieldKind::Choice {
+            options: &["auto", "paru", "yay", "none"],
+            default: "auto",
+        },
+    },
+    Field {
+        key: "terminal",
+        label: "Terminal",
+        page: COMMANDS,
+        kind: FieldKind::Text { default: "auto" },
+    },
+    Field {
+        key: "official_check_cmd",
+        label: "Official check command",
+        page: COMMANDS,
+        kind: FieldKind::Text { default: "auto" },
+    },
+    Field {
+        key: "upgrade_cmd",
+        label: "Upgrade command",
+        page: COMMANDS,
+        kind: FieldKind::Text { default: "auto" },
+    },
+    Field {
+        key: "count_badge",
+        label: "Draw the update count on the icon",
+        page: TRAY,
+        kind: FieldKind::Bool { default: false },
+    },
+    Field {
+        key: "animate_checking",
+        label: "Animate the icon while checking",
+        page: TRAY,
+        kind: FieldKind::Bool { default: true },
+    },
+    Field {
+        key: "label_template",
+        label: "Label ({total}, {official}, {aur})",
+        page: TRAY,
+        kind: FieldKind::Text { default: "" },
+    },
+    Field {
+        key: "tray_backend",
+        label: "Tray backend (after restart)",
+        page: TRAY,
+        kind: FieldKind::Choice {
+            options: &["auto", "appindicator", "sni"],
+            default: "auto",
+        },
+    },
+    icon("icons.checking", "Checking icon"),
+    icon("icons.up_to_date", "Up to date icon"),
+    icon("icons.updates_available", "Updates icon"),
+    icon("icons.overdue", "Overdue icon"),
+    icon("icons.offline", "Offline icon"),
+    icon("icons.error", "Error icon"),
+];
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("failed to read config at {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("failed to parse config at {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml_edit::TomlError,
+    },
+    #[error("{label}: must be between {min} and {max}")]
+    OutOfRange {
+        label: &'static str,
+        min: i64,
+        max: i64,
+    },
+    #[error("{label}: must be one of {}", .options.join(", "))]
+    UnknownChoice {
+        label: &'static str,
+        options: &'static [&'static str],
+    },
+    #[error(transparent)]
+    Invalid(#[from] ConfigError),
+    #[error("failed to write config at {path}: {source}")]
+    Write { path: PathBuf, source: io::Error },
+}
+
+/// The config file as written, so edits keep its comments, layout and unknown keys.
+pub struct ConfigDocument {
+    path: PathBuf,
+    doc: Document,
+}
+
+impl ConfigDocument {
+    /// Reads `path`; a missing file starts out empty.
+    pub fn open(path: &Path) -> Result<Self, SettingsError> {
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(source) => {
+                return Err(SettingsError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+        let doc = raw
+            .parse::<Document>()
+            .map_err(|source| SettingsError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            doc,
+        })
+    }
+
+    /// The value in the file, or the default when the key is missing or has the wrong type.
+    pub fn value(&self, field: &Field) -> FieldValue {
+        self.item(field)
+            .and_then(|item| read_item(field, item))
+            .unwrap_or_else(|| field.default_value())
+    }
+
+    /// Stores `value` unless it matches what the file already says, so untouched keys keep
+    /// their exact formatting and defaults are not written out.
+    pub fn set(&mut self, field: &Field, value: &FieldValue) -> Result<(), SettingsError> {
+        field.check(value)?;
+        if self.value(field) != *value {
+            self.write(field, value);
+        }
+        Ok(())
+    }
+
+    /// Validates the edited file the way pactrack loads it, then replaces the file.
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let raw = self.doc.to_string();
+        parse_config(&raw, &self.path)?;
+
+        let write_error = |source| SettingsError::Write {
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        // Written next to the file and renamed over it, so a reload never sees half a file.
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = self.path.with_file_name(format!(".{name}.tmp"));
+        fs::write(&temp, raw).map_err(write_error)?;
+        fs::rename(&temp, &self.path).map_err(write_error)
+    }
+
+    fn item(&self, field: &Field) -> Option<&Item> {
+        match field.location() {
+            (Some(table), key) => self.doc.get(table)?.get(key),
+            (None, key) => self.doc.get(key),
+        }
+    }
+
+    fn write(&mut self, field: &Field, value: &FieldValue) {
+        let (table, key) = field.location();
+        let table = match table {
+            Some(name) => {
+                let item = self
+                    .doc
+                    .entry(name)
+                    .or_insert_with(|| Item::Table(Table::new()));
+                match item.as_table_mut() {
+                    Some(table) => table,
+                    None => {
+                        *item = Item::Table(Table::new());
+                        item.as_table_mut().expect("just inserted a table")
+                    }
+                }
+            }
+            None => self.doc.as_table_mut(),
+        };
+
+        let Some(mut new) = to_value(field, value) else {
+            table.remove(key);
+            return;
+        };
+        match table.get_mut(key) {
+            Some(item) => {
+                // Keeps trailing comments such as `poll_minutes = 30 # half an hour`.
+                if let Some(old) = item.as_value() {
+                    *new.decor_mut() = old.decor().clone();
+                }
+                *item = Item::Value(new);
+            }
+            None => {
+                table.insert(key, Item::Value(new));
+            }
+        }
+    }
+}
+
+fn read_item(field: &Field, item: &Item) -> Option<FieldValue> {
+    let strings = |item: &Item| -> Option<Vec<String>> {
+        item.as_array()?
+            .iter()
+            .map(|v| v.as_str().map(String::from))
+            .collect()
+    };
+    match field.kind {
+        FieldKind::Bool { .. } => item.as_bool().map(FieldValue::Bool),
+        FieldKind::Integer { .. } | FieldKind::OptionalInteger { .. } => {
+            item.as_integer().map(FieldValue::Integer)
+        }
+        FieldKind::Choice { .. } | FieldKind::Text { .. } => {
+            item.as_str().map(|s| FieldValue::Text(s.to_string()))
+        }
+        FieldKind::List { .. } => strings(item).map(|list| FieldValue::Text(list.join(", "))),
+        FieldKind::Schedule => item
+            .as_str()
+            .map(String::from)
+            .or_else(|| strings(item).map(|times| times.join(", ")))
+            .map(FieldValue::Text),
+        FieldKind::Icon => {
+            if let Some(icon) = item.as_str() {
+                return Some(FieldValue::Icon {
+                    light: icon.to_string(),
+                    dark: String::new(),
+                });
+            }
+            let table = item.as_table_like()?;
+            let variant = |name| {
+                table
+                    .get(name)
+                    .and_then(Item::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            Some(FieldValue::Icon {
+                light: variant("light"),
+                dark: variant("dark"),
+            })
+        }
+    }
+}
+
+/// The TOML value for `value`, or `None` when the key should be left out.
+fn to_value(field: &Field, value: &FieldValue) -> Option<Value> {
+    match (field.kind, value) {
+        (FieldKind::OptionalInteger { .. }, FieldValue::Integer(0)) => None,
+        (_, FieldValue::Bool(v)) => Some(Value::from(*v)),
+        (_, FieldValue::Integer(v)) => Some(Value::from(*v)),
+        (FieldKind::List { .. }, FieldValue::Text(text)) => {
+            Some(Value::Array(split_list(text).collect()))
+        }
+        (FieldKind::Schedule, FieldValue::Text(text)) => {
+            let text = text.trim();
+            if text.is_empty() {
+                return None;
+            }
+            // Cron fields may contain commas too ("0,30 * * * *"), but never a colon.
+            let times: Array = split_list(text).collect();
+            let daily = times
+                .iter()
+                .all(|time| time.as_str().is_some_and(|t| t.contains(':')));
+            Some(if daily {
+                Value::Array(times)
+            } else {
+                Value::from(text)
+            })
+        }
+        (_, FieldValue::Text(text)) => Some(Value::from(text.as_str())),
+        (_, FieldValue::Icon { light, dark }) => {
+            let (light, dark) = (light.trim(), dark.trim());
+            match (light.is_empty(), dark.is_empty()) {
+                (true, true) => None,
+                (false, true) => Some(Value::from(light)),
+                _ if light == dark => Some(Value::from(light)),
+                _ => Some(
+                    [("light", light), ("dark", dark)]
+                        .into_iter()
+                        .filter(|(_, icon)| !icon.is_empty())
+                        .collect(),
+                ),
+            }
+        }
+    }
+}
+
+fn split_list(text: &str) -> impl Iterator<Item = &str> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EffectiveConfig;
+
+    fn field(key: &str) -> &'static Field {
+        FIELDS.iter().find(|f| f.key == key).expect("known field")
+    }
+
+    fn text(value: &str) -> FieldValue {
+        FieldValue::Text(value.to_string())
+    }
+
+    fn saved(doc: &ConfigDocument) -> String {
+        doc.save().expect("save");
+        fs::read_to_string(&doc.path).expect("read back")
+    }
+
+    #[test]
+    fn field_defaults_match_the_built_in_config() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let mut doc = ConfigDocument::open(&temp.path().join("config.toml")).expect("open");
+        for field in FIELDS {
+            doc.write(field, &field.default_value());
+        }
+
+        let parsed = parse_config(&doc.doc.to_string(), &doc.path).expect("valid");
+        assert_eq!(
+            format!("{parsed:?}"),
+            format!("{:?}", EffectiveConfig::default())
+        );
+    }
+
+    #[test]
+    fn edits_keep_comments_and_unknown_keys() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("config.toml");
+        fs::write(
+            &path,
+            "# Refresh interval.\npoll_minutes = 30 # half an hour\nfuture_key = \"kept\"\n\
+             enable_aur = true\n\n[icons]\n# error = \"dialog-error\"\n",
+        )
+        .expect("write config");
+
+        let mut doc = ConfigDocument::open(&path).expect("open");
+        doc.set(field("poll_minutes"), &FieldValue::Integer(45))
+            .expect("set");
+        doc.set(field("enable_aur"), &FieldValue::Bool(true))
+            .expect("set");
+        doc.set(field("notify_on_decrease"), &FieldValue::Bool(true))
+            .expect("set");
+        doc.set(
+            field("icons.overdue"),
+            &FieldValue::Icon {
+                light: "/icons/overdue.svg".to_string(),
+                dark: "/icons/overdue-white.svg".to_string(),
+            },
+        )
+        .expect("set");
+
+        assert_eq!(
+            saved(&doc),
+            "# Refresh interval.\npoll_minutes = 45 # half an hour\nfuture_key = \"kept\"\n\
+             enable_aur = true\nnotify_on_decrease = true\n\n[icons]\n\
+             overdue = { light = \"/icons/overdue.svg\", dark = \"/icons/overdue-white.svg\" }\n\
+             # error = \"dialog-error\"\n"
+        );
+        let doc = ConfigDocument::open(&path).expect("reopen");
+        assert_eq!(doc.value(field("poll_minutes")), FieldValue::Integer(45));
+        assert_eq!(doc.value(field("terminal")), text("auto"));
+    }
+
+    #[test]
+    fn schedules_lists_and_unset_values() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("pactrack").join("config.toml");
+        let mut doc = ConfigDocument::open(&path).expect("open missing file");
+
+        doc.set(field("schedule"), &text("08:00, 18:30"))
+            .expect("set");
+        doc.set(field("aur_schedule"), &text("0,30 */6 * * *"))
+            .expect("set");
+        doc.set(field("quiet_hours"), &text("22:00-07:00,"))
+            .expect("set");
+        doc.set(field("aur_poll_minutes"), &FieldValue::Integer(120))
+            .expect("set");
+        assert_eq!(
+            saved(&doc),
+            "schedule = [\"08:00\", \"18:30\"]\naur_schedule = \"0,30 */6 * * *\"\n\
+             quiet_hours = [\"22:00-07:00\"]\naur_poll_minutes = 120\n"
+        );
+
+        let mut doc = ConfigDocument::open(&path).expect("reopen");
+        assert_eq!(doc.value(field("schedule")), text("08:00, 18:30"));
+        doc.set(field("schedule"), &text("")).expect("set");
+        doc.set(field("aur_poll_minutes"), &FieldValue::Integer(0))
+            .expect("set");
+        assert_eq!(
+            saved(&doc),
+            "aur_schedule = \"0,30 */6 * * *\"\nquiet_hours = [\"22:00-07:00\"]\n"
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_without_writing() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = temp.path().join("config.toml");
+        fs::write(&path, "poll_minutes = 30\n").expect("write config");
+        let mut doc = ConfigDocument::open(&path).expect("open");
+
+        assert!(matches!(
+            doc.set(field("battery_threshold"), &FieldValue::Integer(101)),
+            Err(SettingsError::OutOfRange { max: 100, .. })
+        ));
+        assert!(matches!(
+            doc.set(field("aur_helper"), &text("pikaur")),
+            Err(SettingsError::UnknownChoice { .. })
+        ));
+
+        doc.set(field("quiet_hours"), &text("late")).expect("set");
+        assert!(matches!(doc.save(), Err(SettingsError::Invalid(_))));
+        assert_eq!(
+            fs::read_to_string(&path).expect("read"),
+            "poll_minutes = 30\n"
+        );
+    }
+}
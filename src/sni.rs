@@ -0,0 +1,757 @@
+use std::collections::HashMap;
+use std::thread;
+
+use async_channel::{Receiver, Sender};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::object_server::InterfaceRef;
+use zbus::blocking::{Connection, Proxy};
+use zbus::interface;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Str, Type, Value};
+
+const WATCHER_SERVICE: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+const WATCHER_INTERFACE: &str = "org.kde.StatusNotifierWatcher";
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+
+#[derive(Debug, Error)]
+pub enum SniError {
+    #[error("D-Bus error: {0}")]
+    Bus(#[from] zbus::Error),
+    #[error("no StatusNotifierWatcher on the session bus (is a tray host running?)")]
+    NoWatcher,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ItemStatus {
+    #[default]
+    Active,
+    NeedsAttention,
+}
+
+impl ItemStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "Active",
+            Self::NeedsAttention => "NeedsAttention",
+        }
+    }
+}
+
+/// What the tray host shows for the item.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ItemProps {
+    pub title: String,
+    pub status: ItemStatus,
+    pub icon_name: String,
+    pub attention_icon_name: String,
+    /// Accessible description, also shown as the tooltip.
+    pub description: String,
+    pub icon_theme_path: String,
+    pub label: String,
+    pub label_guide: String,
+}
+
+/// One entry of the exported menu. `id` is the dbusmenu id, unique and non-zero (0 is the
+/// root); it should stay the same for an item across menu changes, since hosts may report a
+/// click on a menu they fetched before the latest change. Activations are reported with it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MenuNode {
+    pub id: i32,
+    pub label: String,
+    pub enabled: bool,
+    pub visible: bool,
+    pub separator: bool,
+    pub children: Vec<MenuNode>,
+}
+
+/// A `(ia{sv}av)` dbusmenu layout node; children are nested layouts wrapped in variants.
+#[derive(Debug, Serialize, Deserialize, Type, Value, zbus::zvariant::OwnedValue)]
+pub struct MenuLayout {
+    pub id: i32,
+    pub properties: HashMap<String, OwnedValue>,
+    pub children: Vec<OwnedValue>,
+}
+
+/// A status notifier item exported on the session bus, for tray hosts that speak the
+/// StatusNotifierItem protocol directly (KDE, waybar, the GNOME AppIndicator extension, ...).
+pub struct SniItem {
+    /// Keeps the bus connection, and with it the exported objects, alive.
+    _conn: Connection,
+    item: InterfaceRef<ItemInterface>,
+    menu: InterfaceRef<MenuInterface>,
+}
+
+impl SniItem {
+    /// Exports the item on `conn` and registers it with the StatusNotifierWatcher.
+    /// The ids of activated menu entries arrive on the returned receiver.
+    pub fn start(conn: Connection, props: ItemProps) -> Result<(Self, Receiver<i32>), SniError> {
+        let (events_tx, events_rx) = async_channel::unbounded();
+        let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+
+        let (item, menu) = {
+            let server = conn.object_server();
+            server.at(ITEM_PATH, ItemInterface { props })?;
+            server.at(
+                MENU_PATH,
+                MenuInterface {
+                    revision: 0,
+                    nodes: Vec::new(),
+                    events: events_tx,
+                },
+            )?;
+            (
+                server.interface::<_, ItemInterface>(ITEM_PATH)?,
+                server.interface::<_, MenuInterface>(MENU_PATH)?,
+            )
+        };
+        conn.request_name(name.as_str())?;
+
+        register_with_watcher(&conn, &name)?;
+        reregister_on_watcher_restart(conn.clone(), name);
+
+        Ok((
+            Self {
+                _conn: conn,
+                item,
+                menu,
+            },
+            events_rx,
+        ))
+    }
+
+    /// Replaces the item properties and signals the host about the parts that changed.
+    pub fn set_props(&self, props: ItemProps) -> Result<(), SniError> {
+        let previous = std::mem::replace(&mut self.item.get_mut().props, props.clone());
+        let emitter = self.item.signal_emitter();
+
+        zbus::block_on(async {
+            if previous.title != props.title {
+                ItemInterface::new_title(emitter).await?;
+            }
+            if previous.icon_name != props.icon_name
+                || previous.icon_theme_path != props.icon_theme_path
+            {
+                ItemInterface::new_icon(emitter).await?;
+            }
+            if previous.attention_icon_name != props.attention_icon_name {
+                ItemInterface::new_attention_icon(emitter).await?;
+            }
+            if previous.description != props.description || previous.title != props.title {
+                ItemInterface::new_tool_tip(emitter).await?;
+            }
+            if previous.status != props.status {
+                ItemInterface::new_status(emitter, props.status.as_str()).await?;
+            }
+            if previous.label != props.label || previous.label_guide != props.label_guide {
+                ItemInterface::x_ayatana_new_label(emitter, &props.label, &props.label_guide)
+                    .await?;
+            }
+            Ok::<(), zbus::Error>(())
+        })?;
+        Ok(())
+    }
+
+    /// Replaces the exported menu. Every change bumps the layout revision and tells hosts
+    /// to reload; an unchanged menu is left alone.
+    pub fn set_menu(&self, nodes: Vec<MenuNode>) -> Result<(), SniError> {
+        let revision = {
+            let mut menu = self.menu.get_mut();
+            if menu.nodes == nodes {
+                return Ok(());
+            }
+            menu.nodes = nodes;
+            menu.revision += 1;
+            menu.revision
+        };
+        zbus::block_on(MenuInterface::layout_updated(
+            self.menu.signal_emitter(),
+            revision,
+            0,
+        ))?;
+        Ok(())
+    }
+}
+
+fn register_with_watcher(conn: &Connection, name: &str) -> Result<(), SniError> {
+    let proxy = Proxy::new(conn, WATCHER_SERVICE, WATCHER_PATH, WATCHER_INTERFACE)?;
+    match proxy.call_method("RegisterStatusNotifierItem", &(name,)) {
+        Ok(_) => Ok(()),
+        Err(zbus::Error::MethodError(error, _, _))
+            if error.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+        {
+            Err(SniError::NoWatcher)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Tray hosts come and go (panel restarts, session switches); register again whenever a
+/// new StatusNotifierWatcher appears.
+fn reregister_on_watcher_restart(conn: Connection, name: String) {
+    let signals = DBusProxy::new(&conn)
+        .and_then(|proxy| proxy.receive_name_owner_changed_with_args(&[(0, WATCHER_SERVICE)]));
+    let signals = match signals {
+        Ok(signals) => signals,
+        Err(err) => {
+            warn!("cannot follow StatusNotifierWatcher restarts: {err}");
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        for signal in signals {
+            let appeared = signal.args().is_ok_and(|args| args.new_owner().is_some());
+            if !appeared {
+                continue;
+            }
+            match register_with_watcher(&conn, &name) {
+                Ok(()) => info!("registered with the new StatusNotifierWatcher"),
+                Err(err) => warn!("failed to register with StatusNotifierWatcher: {err}"),
+            }
+        }
+        debug!("StatusNotifierWatcher signal stream ended");
+    });
+}
+
+struct ItemInterface {
+    props: ItemProps,
+}
+
+type Pixmap = (i32, i32, Vec<u8>);
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl ItemInterface {
+    fn activate(&self, _x: i32, _y: i32) {}
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+    #[zbus(property)]
+    fn category(&self) -> String {
+        "ApplicationStatus".to_string()
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> String {
+        "pactrack".to_string()
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> String {
+        self.props.title.clone()
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> String {
+        self.props.status.as_str().to_string()
+    }
+
+    #[zbus(property)]
+    fn window_id(&self) -> i32 {
+        0
+    }
+
+    #[zbus(property)]
+    fn icon_name(&self) -> String {
+        self.props.icon_name.clone()
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> String {
+        self.props.icon_theme_path.clone()
+    }
+
+    #[zbus(property)]
+    fn icon_pixmap(&self) -> Vec<Pixmap> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn overlay_icon_name(&self) -> String {
+        String::new()
+    }
+
+    #[zbus(property)]
+    fn attention_icon_name(&self) -> String {
+        self.props.attention_icon_name.clone()
+    }
+
+    #[zbus(property)]
+    fn attention_icon_pixmap(&self) -> Vec<Pixmap> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn attention_movie_name(&self) -> String {
+        String::new()
+    }
+
+    #[zbus(property, name = "ToolTip")]
+    fn tool_tip(&self) -> (String, Vec<Pixmap>, String, String) {
+        (
+            self.props.icon_name.clone(),
+            Vec::new(),
+            self.props.title.clone(),
+            self.props.description.clone(),
+        )
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn menu(&self) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(MENU_PATH).expect("valid object path")
+    }
+
+    #[zbus(property, name = "XAyatanaLabel")]
+    fn x_ayatana_label(&self) -> String {
+        self.props.label.clone()
+    }
+
+    #[zbus(property, name = "XAyatanaLabelGuide")]
+    fn x_ayatana_label_guide(&self) -> String {
+        self.props.label_guide.clone()
+    }
+
+    #[zbus(signal)]
+    async fn new_title(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_icon(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_attention_icon(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal, name = "NewToolTip")]
+    async fn new_tool_tip(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_status(emitter: &SignalEmitter<'_>, status: &str) -> zbus::Result<()>;
+
+    #[zbus(signal, name = "XAyatanaNewLabel")]
+    async fn x_ayatana_new_label(
+        emitter: &SignalEmitter<'_>,
+        label: &str,
+        guide: &str,
+    ) -> zbus::Result<()>;
+}
+
+struct MenuInterface {
+    revision: u32,
+    nodes: Vec<MenuNode>,
+    events: Sender<i32>,
+}
+
+impl MenuInterface {
+    /// The node with dbusmenu id `id`; `None` for the root and unknown ids.
+    fn node(&self, id: i32) -> Option<&MenuNode> {
+        fn find(nodes: &[MenuNode], id: i32) -> Option<&MenuNode> {
+            nodes.iter().find_map(|node| {
+                if node.id == id {
+                    Some(node)
+                } else {
+                    find(&node.children, id)
+                }
+            })
+        }
+        if id == 0 { None } else { find(&self.nodes, id) }
+    }
+
+    fn layout(&self, parent: i32, depth: i32) -> MenuLayout {
+        fn build(id: i32, node: Option<&MenuNode>, nodes: &[MenuNode], depth: i32) -> MenuLayout {
+            let children = if depth == 0 {
+                Vec::new()
+            } else {
+                nodes
+                    .iter()
+                    .map(|child| {
+                        let layout = build(child.id, Some(child), &child.children, depth - 1);
+                        OwnedValue::try_from(layout).expect("layout has no fds")
+                    })
+                    .collect()
+            };
+            MenuLayout {
+                id,
+                properties: node.map(node_properties).unwrap_or_else(root_properties),
+                children,
+            }
+        }
+
+        let node = self.node(parent);
+        let children = match node {
+            Some(node) => node.children.as_slice(),
+            None => self.nodes.as_slice(),
+        };
+        let parent = if node.is_some() { parent } else { 0 };
+        build(parent, node, children, depth)
+    }
+
+    fn properties(&self, id: i32) -> HashMap<String, OwnedValue> {
+        self.node(id)
+            .map(node_properties)
+            .unwrap_or_else(root_properties)
+    }
+}
+
+fn root_properties() -> HashMap<String, OwnedValue> {
+    HashMap::from([(
+        "children-display".to_string(),
+        OwnedValue::from(Str::from("submenu")),
+    )])
+}
+
+fn node_properties(node: &MenuNode) -> HashMap<String, OwnedValue> {
+    let mut properties = HashMap::new();
+    if node.separator {
+        properties.insert("type".to_string(), OwnedValue::from(Str::from("separator")));
+    } else {
+        // dbusmenu labels use `_` for mnemonics; package names are taken literally.
+        properties.insert(
+            "label".to_string(),
+            OwnedValue::from(Str::from(node.label.replace('_', "__"))),
+        );
+    }
+    properties.insert("enabled".to_string(), OwnedValue::from(node.enabled));
+    properties.insert("visible".to_string(), OwnedValue::from(node.visible));
+    if !node.children.is_empty() {
+        properties.insert(
+            "children-display".to_string(),
+            OwnedValue::from(Str::from("submenu")),
+        );
+    }
+    properties
+}
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl MenuInterface {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> (u32, MenuLayout) {
+        (self.revision, self.layout(parent_id, recursion_depth))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        ids.into_iter()
+            .map(|id| (id, self.properties(id)))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: &str) -> zbus::fdo::Result<OwnedValue> {
+        self.properties(id)
+            .remove(name)
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("no property {name} on {id}")))
+    }
+
+    fn event(&self, id: i32, event_id: &str, _data: Value<'_>, _timestamp: u32) {
+        if event_id == "clicked" && self.node(id).is_some() {
+            // The receiver is gone only while the tray shuts down.
+            let _ = self.events.try_send(id);
+        }
+    }
+
+    fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        let mut unknown = Vec::new();
+        for (id, event_id, _, _) in events {
+            if self.node(id).is_none() {
+                unknown.push(id);
+            } else if event_id == "clicked" {
+                let _ = self.events.try_send(id);
+            }
+        }
+        unknown
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> String {
+        "ltr".to_string()
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> String {
+        "normal".to_string()
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(signal)]
+    async fn layout_updated(
+        emitter: &SignalEmitter<'_>,
+        revision: u32,
+        parent: i32,
+    ) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::{Arc, Mutex};
+
+    use zbus::blocking::connection::Builder;
+
+    use super::*;
+
+    /// A private session bus, killed when dropped.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// `None` (and the calling test is skipped) when `dbus-daemon` is not installed.
+        fn start() -> Option<Self> {
+            let spawned = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            let mut daemon = match spawned {
+                Ok(daemon) => daemon,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    eprintln!("dbus-daemon not found, skipping");
+                    return None;
+                }
+                Err(err) => panic!("run dbus-daemon: {err}"),
+            };
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take().expect("dbus-daemon stdout"))
+                .read_line(&mut address)
+                .expect("read bus address");
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> Connection {
+            Builder::address(self.address.as_str())
+                .and_then(Builder::build)
+                .expect("connect to private bus")
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct FakeWatcher {
+        registered: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[interface(name = "org.kde.StatusNotifierWatcher")]
+    impl FakeWatcher {
+        fn register_status_notifier_item(&self, service: &str) {
+            self.registered.lock().unwrap().push(service.to_string());
+        }
+    }
+
+    fn start_watcher(bus: &PrivateBus) -> (Connection, FakeWatcher) {
+        let watcher = FakeWatcher::default();
+        let conn = Builder::address(bus.address.as_str())
+            .and_then(|builder| builder.serve_at(WATCHER_PATH, watcher.clone()))
+            .and_then(|builder| builder.name(WATCHER_SERVICE))
+            .and_then(Builder::build)
+            .expect("start fake watcher");
+        (conn, watcher)
+    }
+
+    fn item_name() -> String {
+        format!("org.kde.StatusNotifierItem-{}-1", std::process::id())
+    }
+
+    fn item(id: i32, label: &str) -> MenuNode {
+        MenuNode {
+            id,
+            label: label.to_string(),
+            enabled: true,
+            visible: true,
+            ..MenuNode::default()
+        }
+    }
+
+    fn menu() -> Vec<MenuNode> {
+        vec![
+            item(1, "Status: up to date"),
+            MenuNode {
+                children: vec![item(7, "lib_foo 1-1 → 2-1")],
+                ..item(2, "Official updates")
+            },
+            MenuNode {
+                separator: true,
+                ..item(3, "")
+            },
+            item(4, "Quit"),
+        ]
+    }
+
+    #[test]
+    fn registers_and_exposes_item_properties() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let (_watcher_conn, watcher) = start_watcher(&bus);
+
+        let props = ItemProps {
+            title: "Pactrack".to_string(),
+            icon_name: "pactrack-up-to-date".to_string(),
+            ..ItemProps::default()
+        };
+        let (item, _events) = SniItem::start(bus.connect(), props.clone()).expect("start item");
+        assert_eq!(*watcher.registered.lock().unwrap(), [item_name()]);
+
+        let client = bus.connect();
+        let proxy = Proxy::new(
+            &client,
+            item_name(),
+            ITEM_PATH,
+            "org.kde.StatusNotifierItem",
+        )
+        .expect("item proxy");
+        assert_eq!(proxy.get_property::<String>("Title").unwrap(), "Pactrack");
+        assert_eq!(proxy.get_property::<String>("Status").unwrap(), "Active");
+
+        let mut statuses = proxy.receive_signal("NewStatus").expect("subscribe");
+        item.set_props(ItemProps {
+            status: ItemStatus::NeedsAttention,
+            icon_name: "pactrack-overdue".to_string(),
+            ..props
+        })
+        .expect("update props");
+
+        let signal = statuses.next().expect("NewStatus signal");
+        assert_eq!(
+            signal.body().deserialize::<String>().unwrap(),
+            "NeedsAttention"
+        );
+        // Properties are not cached by the proxy once the host has been told to re-read.
+        let fresh = Proxy::new(
+            &client,
+            item_name(),
+            ITEM_PATH,
+            "org.kde.StatusNotifierItem",
+        )
+        .expect("item proxy");
+        assert_eq!(
+            fresh.get_property::<String>("IconName").unwrap(),
+            "pactrack-overdue"
+        );
+    }
+
+    #[test]
+    fn exports_the_menu_and_reports_clicks() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let (_watcher_conn, _watcher) = start_watcher(&bus);
+        let (item, events) =
+            SniItem::start(bus.connect(), ItemProps::default()).expect("start item");
+        item.set_menu(menu()).expect("set menu");
+
+        let client = bus.connect();
+        let proxy = Proxy::new(&client, item_name(), MENU_PATH, "com.canonical.dbusmenu")
+            .expect("menu proxy");
+        let (revision, root): (u32, MenuLayout) = proxy
+            .call("GetLayout", &(0i32, -1i32, Vec::<String>::new()))
+            .expect("GetLayout");
+        assert_eq!(revision, 1);
+
+        let children: Vec<MenuLayout> = root
+            .children
+            .into_iter()
+            .map(|child| MenuLayout::try_from(child).expect("layout"))
+            .collect();
+        let ids: Vec<i32> = children.iter().map(|child| child.id).collect();
+        assert_eq!(ids, [1, 2, 3, 4]);
+
+        let submenu = MenuLayout::try_from(children[1].children[0].try_clone().unwrap())
+            .expect("submenu layout");
+        assert_eq!(submenu.id, 7);
+        let label: String = submenu.properties["label"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(label, "lib__foo 1-1 → 2-1");
+        let kind: String = children[2].properties["type"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(kind, "separator");
+
+        let click = |id: i32| {
+            proxy
+                .call_method("Event", &(id, "clicked", Value::from(0i32), 0u32))
+                .expect("Event");
+        };
+        click(7);
+        assert_eq!(events.recv_blocking(), Ok(7));
+
+        // Unchanged menus do not bump the revision.
+        item.set_menu(menu()).expect("set menu");
+        let revision = |proxy: &Proxy| {
+            let (revision, _): (u32, MenuLayout) = proxy
+                .call("GetLayout", &(0i32, 0i32, Vec::<String>::new()))
+                .expect("GetLayout");
+            revision
+        };
+        assert_eq!(revision(&proxy), 1);
+
+        // Once the package is gone, a click on its id from an old layout is dropped, and
+        // the remaining items keep their ids.
+        let mut changed = menu();
+        changed[1].children.clear();
+        item.set_menu(changed).expect("set menu");
+        assert_eq!(revision(&proxy), 2);
+        click(7);
+        click(4);
+        assert_eq!(events.recv_blocking(), Ok(4));
+    }
+
+    #[test]
+    fn fails_without_a_watcher() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let result = SniItem::start(bus.connect(), ItemProps::default());
+        assert!(matches!(result, Err(SniError::NoWatcher)));
+    }
+}
@@ -1,40 +1,198 @@
+use std::iter;
+
 use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Status {
     Checking,
     UpToDate,
     UpdatesAvailable,
+    Overdue,
+    Offline,
     Error,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum UpdateSource {
     Official,
     Aur,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+impl UpdateSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Official => "Official",
+            Self::Aur => "AUR",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum InstallReason {
+    #[default]
+    Explicit,
+    Dependency,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct PackageUpdate {
     pub name: String,
     pub current: String,
     pub latest: String,
     pub source: UpdateSource,
+    #[serde(default)]
+    pub reason: InstallReason,
+    #[serde(default)]
+    pub first_seen: Option<DateTime<Local>>,
+    /// Sync repository (e.g. `core`); only known for official updates.
+    #[serde(default)]
+    pub repo: Option<String>,
+    /// Download size in bytes as reported by `pacman -Si`.
+    #[serde(default)]
+    pub download_size: Option<u64>,
+}
+
+impl PackageUpdate {
+    pub fn new(name: &str, current: &str, latest: &str, source: UpdateSource) -> Self {
+        Self {
+            name: name.to_string(),
+            current: current.to_string(),
+            latest: latest.to_string(),
+            source,
+            reason: InstallReason::Explicit,
+            first_seen: None,
+            repo: None,
+            download_size: None,
+        }
+    }
+
+    pub fn is_explicit(&self) -> bool {
+        self.reason == InstallReason::Explicit
+    }
+
+    pub fn pending_days(&self, now: DateTime<Local>) -> Option<i64> {
+        self.first_seen.map(|seen| (now - seen).num_days())
+    }
+
+    /// The package's page on archlinux.org or the AUR.
+    pub fn web_url(&self) -> String {
+        match self.source {
+            UpdateSource::Official => format!(
+                "https://archlinux.org/packages/?name={}",
+                self.name.replace('+', "%2B")
+            ),
+            UpdateSource::Aur => format!("https://aur.archlinux.org/packages/{}", self.name),
+        }
+    }
+}
+
+/// An updated package that installed packages depend on, with the AUR packages that may
+/// need a rebuild afterwards.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RebuildHint {
+    pub package: String,
+    pub current: String,
+    pub latest: String,
+    pub dependents: usize,
+    pub aur_dependents: Vec<String>,
+    pub minor_bump: bool,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct UpdateSnapshot {
     pub official: Vec<PackageUpdate>,
     pub aur: Vec<PackageUpdate>,
+    #[serde(default)]
+    pub rebuilds: Vec<RebuildHint>,
+    /// Pending updates the user snoozed; kept out of `official`/`aur` and the counts.
+    #[serde(default)]
+    pub snoozed: Vec<PackageUpdate>,
 }
 
 impl UpdateSnapshot {
     pub fn total_count(&self) -> usize {
         self.official.len() + self.aur.len()
     }
+
+    pub fn all(&self) -> impl Iterator<Item = &PackageUpdate> {
+        self.official.iter().chain(self.aur.iter())
+    }
+
+    /// Moves snoozed updates back into their source lists.
+    pub fn unsnooze(&mut self) {
+        for update in self.snoozed.drain(..) {
+            match update.source {
+                UpdateSource::Official => self.official.push(update),
+                UpdateSource::Aur => self.aur.push(update),
+            }
+        }
+    }
+
+    pub fn oldest_pending(&self) -> Option<&PackageUpdate> {
+        self.all()
+            .filter(|update| update.first_seen.is_some())
+            .min_by_key(|update| update.first_seen)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceStatus {
+    NotChecked,
+    Checking,
+    /// The latest check, at the given time, succeeded.
+    Ok(DateTime<Local>),
+    Failed,
+}
+
+/// Outcome of the latest check of one update source; each source runs on its own schedule.
+/// After a failure, the source's counts in the snapshot are those of `last_success`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SourceState {
+    #[serde(default)]
+    pub last_checked: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub last_success: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    #[serde(default)]
+    pub next_check: Option<DateTime<Local>>,
+    #[serde(skip)]
+    pub checking: bool,
+}
+
+impl SourceState {
+    pub fn status(&self) -> SourceStatus {
+        if self.checking {
+            SourceStatus::Checking
+        } else if self.last_error.is_some() {
+            SourceStatus::Failed
+        } else if let Some(checked) = self.last_checked {
+            SourceStatus::Ok(checked)
+        } else {
+            SourceStatus::NotChecked
+        }
+    }
+
+    pub fn succeeded(&mut self, checked_at: DateTime<Local>) {
+        self.last_checked = Some(checked_at);
+        self.last_success = Some(checked_at);
+        self.last_error = None;
+        self.consecutive_failures = 0;
+        self.checking = false;
+    }
+
+    pub fn failed(&mut self, message: String, checked_at: DateTime<Local>) {
+        self.last_checked = Some(checked_at);
+        self.last_error = Some(message);
+        self.consecutive_failures += 1;
+        self.checking = false;
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AppState {
     pub status: Status,
     pub official_count: usize,
@@ -42,6 +200,23 @@ pub struct AppState {
     pub total_count: usize,
     pub last_checked: Option<DateTime<Local>>,
     pub last_error: Option<String>,
+    #[serde(default)]
+    pub oldest_pending: Option<(String, DateTime<Local>)>,
+    #[serde(default)]
+    pub next_check: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Set while the state comes from the on-disk cache rather than a check in this session.
+    #[serde(default)]
+    pub stale: bool,
+    /// With `Status::Offline`: the network is up but metered, and checks are paused.
+    #[serde(default)]
+    pub metered: bool,
+    #[serde(default)]
+    pub official: SourceState,
+    /// `None` when AUR checks are disabled.
+    #[serde(default)]
+    pub aur: Option<SourceState>,
 }
 
 impl Default for AppState {
@@ -53,39 +228,227 @@ impl Default for AppState {
             total_count: 0,
             last_checked: None,
             last_error: None,
+            oldest_pending: None,
+            next_check: None,
+            consecutive_failures: 0,
+            stale: false,
+            metered: false,
+            official: SourceState::default(),
+            aur: None,
         }
     }
 }
 
 impl AppState {
-    pub fn from_snapshot(snapshot: &UpdateSnapshot, checked_at: DateTime<Local>) -> Self {
+    /// Combines the merged snapshot with the state of each source. A failing source only
+    /// degrades to `Error` when every source failed; otherwise the status follows the counts
+    /// and the failure is reported per source. `Checking` applies only while every source
+    /// is being checked, so results of a fast source show up before a slow one finishes.
+    pub fn from_sources(
+        snapshot: &UpdateSnapshot,
+        official: SourceState,
+        aur: Option<SourceState>,
+    ) -> Self {
         let total = snapshot.total_count();
-        let status = if total == 0 {
+        let sources: Vec<(UpdateSource, &SourceState)> =
+            iter::once((UpdateSource::Official, &official))
+                .chain(aur.as_ref().map(|state| (UpdateSource::Aur, state)))
+                .collect();
+
+        let mut status = if total == 0 {
             Status::UpToDate
         } else {
             Status::UpdatesAvailable
         };
+        let all_failed = sources
+            .iter()
+            .all(|(_, state)| state.status() == SourceStatus::Failed);
+        let failing: Vec<&(UpdateSource, &SourceState)> =
+            sources.iter().filter(|_| all_failed).collect();
+        let errors: Vec<String> = failing
+            .iter()
+            .filter_map(|(source, state)| {
+                Some(format!(
+                    "{}: {}",
+                    source.label(),
+                    state.last_error.as_ref()?
+                ))
+            })
+            .collect();
+        let last_error = (!errors.is_empty()).then(|| errors.join("; "));
+        let consecutive_failures = failing
+            .iter()
+            .map(|(_, state)| state.consecutive_failures)
+            .max()
+            .unwrap_or(0);
+        if !failing.is_empty() {
+            status = Status::Error;
+        }
+        if sources
+            .iter()
+            .all(|(_, state)| state.status() == SourceStatus::Checking)
+        {
+            status = Status::Checking;
+        }
+        let last_checked = sources
+            .iter()
+            .filter_map(|(_, state)| state.last_checked)
+            .max();
+        let next_check = sources
+            .iter()
+            .filter_map(|(_, state)| state.next_check)
+            .min();
 
         Self {
             status,
             official_count: snapshot.official.len(),
             aur_count: snapshot.aur.len(),
             total_count: total,
-            last_checked: Some(checked_at),
-            last_error: None,
+            last_checked,
+            last_error,
+            oldest_pending: snapshot
+                .oldest_pending()
+                .and_then(|update| Some((update.name.clone(), update.first_seen?))),
+            next_check,
+            consecutive_failures,
+            stale: false,
+            metered: false,
+            official,
+            aur,
         }
     }
 
-    pub fn with_error(mut self, message: String, checked_at: DateTime<Local>) -> Self {
-        self.status = Status::Error;
-        self.last_checked = Some(checked_at);
-        self.last_error = Some(message);
+    /// Sources whose latest check failed.
+    pub fn failed_sources(&self) -> Vec<UpdateSource> {
+        iter::once((UpdateSource::Official, Some(&self.official)))
+            .chain(iter::once((UpdateSource::Aur, self.aur.as_ref())))
+            .filter(|(_, state)| state.is_some_and(|state| state.status() == SourceStatus::Failed))
+            .map(|(source, _)| source)
+            .collect()
+    }
+
+    /// True while any source has a check in flight.
+    pub fn is_checking(&self) -> bool {
+        self.official.checking || self.aur.as_ref().is_some_and(|aur| aur.checking)
+    }
+
+    /// Raises `UpdatesAvailable` to `Overdue` once the oldest pending update is older than
+    /// `warn_days` (0 disables the check).
+    pub fn with_pending_threshold(mut self, warn_days: u64, now: DateTime<Local>) -> Self {
+        let overdue = self
+            .oldest_pending
+            .as_ref()
+            .is_some_and(|(_, seen)| warn_days > 0 && (now - *seen).num_days() >= warn_days as i64);
+
+        if overdue && self.status == Status::UpdatesAvailable {
+            self.status = Status::Overdue;
+        }
         self
     }
 
-    pub fn with_checking(mut self) -> Self {
-        self.status = Status::Checking;
-        self.last_error = None;
+    /// Keeps the last counts but marks checks as paused for lack of (unmetered) network.
+    pub fn with_offline(mut self, metered: bool) -> Self {
+        self.status = Status::Offline;
+        self.metered = metered;
         self
     }
+
+    pub fn with_stale(mut self) -> Self {
+        self.stale = true;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> UpdateSnapshot {
+        UpdateSnapshot {
+            official: vec![PackageUpdate::new(
+                "firefox",
+                "121.0-1",
+                "122.0-1",
+                UpdateSource::Official,
+            )],
+            ..UpdateSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn failing_aur_keeps_official_results_and_reports_per_source() {
+        let now = Local::now();
+        let mut official = SourceState::default();
+        official.succeeded(now);
+        let mut aur = SourceState::default();
+        aur.failed("paru exited with 1".to_string(), now);
+        aur.next_check = Some(now + chrono::Duration::minutes(5));
+
+        let state = AppState::from_sources(&snapshot(), official, Some(aur));
+
+        assert_eq!(state.status, Status::UpdatesAvailable);
+        assert_eq!(state.official_count, 1);
+        assert!(state.last_error.is_none());
+        assert_eq!(state.failed_sources(), vec![UpdateSource::Aur]);
+        assert_eq!(state.next_check, Some(now + chrono::Duration::minutes(5)));
+    }
+
+    #[test]
+    fn every_source_failing_is_an_error_with_both_messages() {
+        let now = Local::now();
+        let mut official = SourceState::default();
+        official.failed("mirror down".to_string(), now);
+        let mut aur = SourceState::default();
+        aur.failed("paru exited with 1".to_string(), now);
+        aur.failed("paru exited with 1".to_string(), now);
+
+        let state = AppState::from_sources(&snapshot(), official, Some(aur));
+
+        assert_eq!(state.status, Status::Error);
+        assert_eq!(
+            state.last_error.as_deref(),
+            Some("Official: mirror down; AUR: paru exited with 1")
+        );
+        assert_eq!(state.consecutive_failures, 2);
+    }
+
+    #[test]
+    fn checking_only_while_every_source_is_checking() {
+        let official = SourceState {
+            checking: true,
+            ..SourceState::default()
+        };
+        let aur = SourceState::default();
+
+        let partial = AppState::from_sources(&snapshot(), official.clone(), Some(aur.clone()));
+        assert_eq!(partial.status, Status::UpdatesAvailable);
+
+        let aur = SourceState {
+            checking: true,
+            ..aur
+        };
+        let all = AppState::from_sources(&snapshot(), official, Some(aur));
+        assert_eq!(all.status, Status::Checking);
+    }
+
+    #[test]
+    fn overdue_status_follows_threshold() {
+        let now = Local::now();
+        let mut current = snapshot();
+        current.official[0].first_seen = Some(now - chrono::Duration::days(15));
+
+        let state = AppState::from_sources(&current, SourceState::default(), None);
+        assert_eq!(
+            state.clone().with_pending_threshold(14, now).status,
+            Status::Overdue
+        );
+        assert_eq!(
+            state.clone().with_pending_threshold(0, now).status,
+            Status::UpdatesAvailable
+        );
+        assert_eq!(
+            state.with_pending_threshold(30, now).status,
+            Status::UpdatesAvailable
+        );
+    }
 }
@@ -1,77 +1,86 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::ffi::{CString, c_char, c_int, c_void};
 use std::path::Path;
 use std::process::Child;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::slice;
 use std::sync::{Arc, mpsc};
 use std::thread;
 use std::time::Duration;
 
+use chrono::Local;
 use glib::ControlFlow;
 use gtk::prelude::*;
 use libloading::Library;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
+use crate::checklist::open_upgrade_checklist;
 use crate::commands::{
-    DetectedAurHelper, build_details_shell_command, build_upgrade_aur_shell_command,
-    build_upgrade_official_shell_command, build_upgrade_shell_command, launch_in_terminal,
-    launch_in_terminal_process,
+    DetectedAurHelper, build_upgrade_aur_shell_command, build_upgrade_official_shell_command,
+    build_upgrade_shell_command, launch_in_terminal_process,
 };
-use crate::config::EffectiveConfig;
+use crate::config::{ConfigSource, EffectiveConfig, TrayBackend};
+use crate::details::DetailsWindow;
 use crate::icons;
-use crate::notifier;
+use crate::notifier::{self, Announcer};
+use crate::preferences::open_preferences;
+use crate::report::{pending_days_label, render_label, status_text, truncate, truncate_error};
 use crate::scheduler::{SchedulerCommand, SchedulerUpdate, start_scheduler};
-use crate::state::{AppState, Status, UpdateSnapshot};
+use crate::sni::{ItemProps, ItemStatus, MenuNode, SniItem};
+use crate::state::{AppState, PackageUpdate, SourceState, SourceStatus, Status, UpdateSnapshot};
+use crate::upgrade::{Selection, selection_key};
 
-pub fn run(config: EffectiveConfig) -> Result<(), String> {
+pub fn run(config: EffectiveConfig, config_source: ConfigSource) -> Result<(), String> {
     gtk::init().map_err(|e| format!("failed to initialize GTK: {e}"))?;
 
     let icon_dir = icons::install_fallback_icons()
         .map_err(|e| format!("failed to install fallback icons: {e}"))?;
 
-    let api = Arc::new(AppIndicatorApi::load()?);
-    let indicator = AppIndicator::new(api, "pactrack", "software-update-available")?;
-    indicator.set_status_active();
+    let indicator = Indicator::create(config.tray_backend)?;
+    indicator.set_title("Pactrack");
+    indicator.set_status(ItemStatus::Active);
     indicator.set_icon_theme_path(&icon_dir);
 
     let menu = gtk::Menu::new();
-    let status_item = gtk::MenuItem::with_label("Status: checking");
-    status_item.set_sensitive(false);
-
-    let official_item = gtk::MenuItem::with_label("Official updates: 0");
-    official_item.set_sensitive(false);
-
-    let aur_item = gtk::MenuItem::with_label("AUR updates: 0");
-    aur_item.set_sensitive(false);
-
-    let checked_item = gtk::MenuItem::with_label("Last check: never");
-    checked_item.set_sensitive(false);
+    let status_items = StatusItems::new();
+    let config_error_item = gtk::MenuItem::with_label("Config error");
+    config_error_item.set_sensitive(false);
 
     let refresh_item = gtk::MenuItem::with_label("Refresh now");
+    let cancel_item = gtk::MenuItem::with_label("Cancel check");
     let details_item = gtk::MenuItem::with_label("Open details");
     let upgrade_item = gtk::MenuItem::with_label("Upgrade all");
+    let upgrade_selected_item = gtk::MenuItem::with_label("Choose updates to install…");
     let upgrade_official_item = gtk::MenuItem::with_label("Upgrade official only");
     let upgrade_aur_item = gtk::MenuItem::with_label("Upgrade AUR only");
     upgrade_aur_item.set_sensitive(false);
+    let preferences_item = gtk::MenuItem::with_label("Preferences…");
     let quit_item = gtk::MenuItem::with_label("Quit");
 
-    menu.append(&status_item);
-    menu.append(&official_item);
-    menu.append(&aur_item);
-    menu.append(&checked_item);
+    status_items.append_to(&menu);
+    menu.append(&config_error_item);
     menu.append(&gtk::SeparatorMenuItem::new());
     menu.append(&refresh_item);
+    menu.append(&cancel_item);
     menu.append(&details_item);
     menu.append(&upgrade_item);
+    menu.append(&upgrade_selected_item);
     menu.append(&upgrade_official_item);
     menu.append(&upgrade_aur_item);
     menu.append(&gtk::SeparatorMenuItem::new());
+    menu.append(&preferences_item);
     menu.append(&quit_item);
     menu.show_all();
+    cancel_item.set_visible(false);
+    config_error_item.set_visible(false);
     indicator.set_menu(&menu);
 
     let (updates_tx, updates_rx) = mpsc::channel::<SchedulerUpdate>();
-    let scheduler_tx = start_scheduler(config.clone(), updates_tx);
+    let scheduler_tx = start_scheduler(config.clone(), config_source.clone(), updates_tx);
+
+    // Replaced whenever the scheduler reports a reloaded config file.
+    let config = Rc::new(RefCell::new(config));
 
     {
         let scheduler_tx = scheduler_tx.clone();
@@ -82,37 +91,41 @@ pub fn run(config: EffectiveConfig) -> Result<(), String> {
         });
     }
 
-    #[derive(Default)]
-    struct RuntimeState {
-        previous_total_count: Option<usize>,
-        helper: Option<DetectedAurHelper>,
-        _snapshot: Option<UpdateSnapshot>,
+    {
+        let scheduler_tx = scheduler_tx.clone();
+        cancel_item.connect_activate(move |_| {
+            if scheduler_tx.send(SchedulerCommand::Cancel).is_err() {
+                error!("failed to send cancel command to scheduler");
+            }
+        });
     }
 
     let runtime_state = Rc::new(RefCell::new(RuntimeState::default()));
+    let package_actions = PackageActions {
+        config: Rc::clone(&config),
+        runtime_state: Rc::clone(&runtime_state),
+        scheduler_tx: scheduler_tx.clone(),
+        details: Rc::default(),
+    };
 
     {
-        let runtime_state = Rc::clone(&runtime_state);
-        let cfg = config.clone();
-        details_item.connect_activate(move |_| {
-            let helper = runtime_state.borrow().helper;
-            match build_details_shell_command(&cfg, helper)
-                .and_then(|command| launch_in_terminal(&cfg, &command))
-            {
-                Ok(()) => info!("opened details terminal"),
-                Err(err) => error!("failed to open details terminal: {err}"),
-            }
-        });
+        let actions = package_actions.clone();
+        details_item.connect_activate(move |_| actions.open_details());
+    }
+
+    {
+        let actions = package_actions.clone();
+        upgrade_selected_item.connect_activate(move |_| actions.choose_upgrades(None));
     }
 
     {
         let runtime_state = Rc::clone(&runtime_state);
-        let cfg = config.clone();
+        let cfg = Rc::clone(&config);
         let scheduler_tx = scheduler_tx.clone();
         upgrade_item.connect_activate(move |_| {
             let helper = runtime_state.borrow().helper;
-            let command = build_upgrade_shell_command(&cfg, helper);
-            match launch_in_terminal_process(&cfg, &command) {
+            let command = build_upgrade_shell_command(&cfg.borrow(), helper);
+            match launch_in_terminal_process(&cfg.borrow(), &command) {
                 Ok(child) => {
                     info!("opened upgrade terminal");
                     queue_refresh_when_process_exits(child, scheduler_tx.clone());
@@ -123,11 +136,11 @@ pub fn run(config: EffectiveConfig) -> Result<(), String> {
     }
 
     {
-        let cfg = config.clone();
+        let cfg = Rc::clone(&config);
         let scheduler_tx = scheduler_tx.clone();
         upgrade_official_item.connect_activate(move |_| {
             let command = build_upgrade_official_shell_command();
-            match launch_in_terminal_process(&cfg, &command) {
+            match launch_in_terminal_process(&cfg.borrow(), &command) {
                 Ok(child) => {
                     info!("opened official upgrade terminal");
                     queue_refresh_when_process_exits(child, scheduler_tx.clone());
@@ -139,7 +152,7 @@ pub fn run(config: EffectiveConfig) -> Result<(), String> {
 
     {
         let runtime_state = Rc::clone(&runtime_state);
-        let cfg = config.clone();
+        let cfg = Rc::clone(&config);
         let scheduler_tx = scheduler_tx.clone();
         upgrade_aur_item.connect_activate(move |_| {
             let helper = runtime_state.borrow().helper;
@@ -148,7 +161,7 @@ pub fn run(config: EffectiveConfig) -> Result<(), String> {
                 return;
             };
 
-            match launch_in_terminal_process(&cfg, &command) {
+            match launch_in_terminal_process(&cfg.borrow(), &command) {
                 Ok(child) => {
                     info!("opened AUR upgrade terminal");
                     queue_refresh_when_process_exits(child, scheduler_tx.clone());
@@ -158,49 +171,85 @@ pub fn run(config: EffectiveConfig) -> Result<(), String> {
         });
     }
 
+    {
+        let scheduler_tx = scheduler_tx.clone();
+        let open_dialog = RefCell::new(None::<gtk::Dialog>);
+        preferences_item.connect_activate(move |_| {
+            if let Some(dialog) = open_dialog.borrow().as_ref()
+                && dialog.is_visible()
+            {
+                dialog.present();
+                return;
+            }
+            let (config_source, scheduler_tx) = (config_source.clone(), scheduler_tx.clone());
+            let path = config_source.path.clone();
+            let dialog = open_preferences(&path, move || {
+                // The file watcher reloads too, but may be unavailable (e.g. out of watches).
+                let result = config_source
+                    .load()
+                    .map(Box::new)
+                    .map_err(|e| e.to_string());
+                if scheduler_tx
+                    .send(SchedulerCommand::Reconfigure(result))
+                    .is_err()
+                {
+                    error!("failed to send reloaded config to scheduler");
+                }
+            });
+            *open_dialog.borrow_mut() = Some(dialog);
+        });
+    }
+
     quit_item.connect_activate(move |_| {
         gtk::main_quit();
     });
 
-    let status_item_ref = status_item.clone();
-    let official_item_ref = official_item.clone();
-    let aur_item_ref = aur_item.clone();
-    let checked_item_ref = checked_item.clone();
     let upgrade_aur_item_ref = upgrade_aur_item.clone();
+    let cancel_item_ref = cancel_item.clone();
     let indicator_ref = indicator.clone();
-    let notify_enabled = config.notify_on_change;
-    let enable_aur = config.enable_aur;
+    let animation = CheckingAnimation::default();
+    let mut announcer = Announcer::default();
 
     glib::timeout_add_local(Duration::from_millis(350), move || {
         while let Ok(update) = updates_rx.try_recv() {
+            if let Some(new_config) = update.config {
+                info!("applying reloaded config");
+                *config.borrow_mut() = new_config;
+            }
+            apply_config_error(&config_error_item, update.config_error.as_deref());
+
             apply_update_to_menu(
                 &indicator_ref,
-                &status_item_ref,
-                &official_item_ref,
-                &aur_item_ref,
-                &checked_item_ref,
+                &status_items,
                 &update.state,
                 &icon_dir,
+                &config.borrow(),
+                &animation,
             );
+            cancel_item_ref.set_visible(update.state.is_checking());
 
+            let snapshot_changed = update.snapshot.is_some();
+            let cfg = config.borrow();
             let mut rt = runtime_state.borrow_mut();
             rt.helper = update.helper;
-            upgrade_aur_item_ref.set_sensitive(enable_aur && rt.helper.is_some());
+            upgrade_aur_item_ref.set_sensitive(cfg.enable_aur && rt.helper.is_some());
             if let Some(snapshot) = update.snapshot {
-                rt._snapshot = Some(snapshot);
+                announcer.observe(&snapshot);
+                rt.snapshot = Some(snapshot);
             }
+            drop(rt);
+            drop(cfg);
 
-            if notify_enabled {
-                if update.state.status != Status::Checking {
-                    if let Some(prev) = rt.previous_total_count {
-                        if prev != update.state.total_count {
-                            notifier::notify_count_change(prev, update.state.total_count);
-                        }
-                    }
-                    rt.previous_total_count = Some(update.state.total_count);
-                }
+            if snapshot_changed && let Some(snapshot) = runtime_state.borrow().snapshot.as_ref() {
+                status_items.set_packages(snapshot, &package_actions);
+                package_actions.refresh_details(snapshot);
             }
         }
+        // Also runs without new results, so updates held back by quiet hours are announced
+        // as soon as the window ends.
+        if let Some(body) = announcer.take_message(&config.borrow(), Local::now()) {
+            notifier::notify(&body);
+        }
         ControlFlow::Continue
     });
 
@@ -227,67 +276,676 @@ fn queue_refresh_when_process_exits(child: Child, scheduler_tx: mpsc::Sender<Sch
     });
 }
 
+#[derive(Default)]
+struct RuntimeState {
+    helper: Option<DetectedAurHelper>,
+    snapshot: Option<UpdateSnapshot>,
+}
+
+/// Pending updates listed per source before the rest is folded into a "more…" entry.
+const MENU_PACKAGE_LIMIT: usize = 20;
+
+/// Builds the per-package submenus and the details window, and runs their actions.
+#[derive(Clone)]
+struct PackageActions {
+    config: Rc<RefCell<EffectiveConfig>>,
+    runtime_state: Rc<RefCell<RuntimeState>>,
+    scheduler_tx: mpsc::Sender<SchedulerCommand>,
+    /// Created on first use and hidden rather than destroyed when closed.
+    details: Rc<RefCell<Option<DetailsWindow>>>,
+}
+
+impl PackageActions {
+    /// One entry per update (`name current → latest`), each with its own action submenu.
+    fn package_list(&self, updates: &[PackageUpdate]) -> gtk::Menu {
+        let menu = gtk::Menu::new();
+        for update in updates.iter().take(MENU_PACKAGE_LIMIT) {
+            let item = gtk::MenuItem::with_label(&format!(
+                "{} {} → {}",
+                update.name, update.current, update.latest
+            ));
+            item.set_submenu(Some(&self.package_menu(update)));
+            menu.append(&item);
+        }
+
+        if updates.len() > MENU_PACKAGE_LIMIT {
+            let more =
+                gtk::MenuItem::with_label(&format!("{} more…", updates.len() - MENU_PACKAGE_LIMIT));
+            let actions = self.clone();
+            more.connect_activate(move |_| actions.open_details());
+            menu.append(&more);
+        }
+
+        menu.show_all();
+        menu
+    }
+
+    fn package_menu(&self, update: &PackageUpdate) -> gtk::Menu {
+        let menu = gtk::Menu::new();
+
+        let web_item = gtk::MenuItem::with_label("Open web page");
+        let url = update.web_url();
+        web_item.connect_activate(move |_| {
+            if let Err(err) =
+                gtk::show_uri_on_window(None::<&gtk::Window>, &url, gtk::current_event_time())
+            {
+                error!("failed to open {url}: {err}");
+            }
+        });
+
+        let snooze_item = gtk::MenuItem::with_label("Snooze this version for a week");
+        {
+            let scheduler_tx = self.scheduler_tx.clone();
+            let (source, name, latest) =
+                (update.source, update.name.clone(), update.latest.clone());
+            snooze_item.connect_activate(move |_| {
+                let command = SchedulerCommand::Snooze {
+                    source,
+                    name: name.clone(),
+                    latest: latest.clone(),
+                };
+                if scheduler_tx.send(command).is_err() {
+                    error!("failed to send snooze command to scheduler");
+                }
+            });
+        }
+
+        let upgrade_item = gtk::MenuItem::with_label("Upgrade this package…");
+        {
+            let actions = self.clone();
+            let update = update.clone();
+            upgrade_item
+                .connect_activate(move |_| actions.upgrade_packages(slice::from_ref(&update)));
+        }
+
+        menu.append(&web_item);
+        menu.append(&snooze_item);
+        menu.append(&upgrade_item);
+        menu.show_all();
+        menu
+    }
+
+    /// Opens the upgrade checklist with just `updates` ticked.
+    fn upgrade_packages(&self, updates: &[PackageUpdate]) {
+        let selection = updates.iter().map(selection_key).collect();
+        self.choose_upgrades(Some(selection));
+    }
+
+    /// Opens the upgrade checklist; `None` ticks every pending update.
+    fn choose_upgrades(&self, preselected: Option<Selection>) {
+        let rt = self.runtime_state.borrow();
+        let Some(snapshot) = rt.snapshot.as_ref() else {
+            info!("no completed check yet, nothing to choose from");
+            return;
+        };
+        let preselected =
+            preselected.unwrap_or_else(|| snapshot.all().map(selection_key).collect());
+
+        let upgrade_cmd = self.config.borrow().upgrade_cmd.clone();
+        let actions = self.clone();
+        open_upgrade_checklist(
+            snapshot,
+            &preselected,
+            rt.helper,
+            upgrade_cmd,
+            move |command| actions.launch_upgrade(&command),
+        );
+    }
+
+    fn launch_upgrade(&self, command: &str) {
+        match launch_in_terminal_process(&self.config.borrow(), command) {
+            Ok(child) => {
+                info!("opened upgrade terminal for the selected packages");
+                queue_refresh_when_process_exits(child, self.scheduler_tx.clone());
+            }
+            Err(err) => error!("failed to open upgrade terminal: {err}"),
+        }
+    }
+
+    fn open_details(&self) {
+        let mut details = self.details.borrow_mut();
+        let window = details.get_or_insert_with(|| {
+            let actions = self.clone();
+            DetailsWindow::new(move |updates| actions.upgrade_packages(&updates))
+        });
+        window.present(self.runtime_state.borrow().snapshot.as_ref());
+    }
+
+    /// Keeps an open details window in step with the latest check.
+    fn refresh_details(&self, snapshot: &UpdateSnapshot) {
+        if let Some(window) = self.details.borrow().as_ref()
+            && window.is_visible()
+        {
+            window.set_snapshot(Some(snapshot));
+        }
+    }
+}
+
+/// Read-only status lines at the top of the tray menu. The update count lines open a list
+/// of the pending packages when there are any.
+struct StatusItems {
+    status: gtk::MenuItem,
+    official: gtk::MenuItem,
+    aur: gtk::MenuItem,
+    next: gtk::MenuItem,
+    pending: gtk::MenuItem,
+}
+
+impl StatusItems {
+    fn new() -> Self {
+        let items = Self {
+            status: gtk::MenuItem::with_label("Status: checking"),
+            official: gtk::MenuItem::with_label("Official updates: 0"),
+            aur: gtk::MenuItem::with_label("AUR updates: 0"),
+            next: gtk::MenuItem::with_label("Next check: pending"),
+            pending: gtk::MenuItem::with_label("Oldest pending: none"),
+        };
+
+        for item in items.all() {
+            item.set_sensitive(false);
+        }
+        items
+    }
+
+    fn all(&self) -> [&gtk::MenuItem; 5] {
+        [
+            &self.status,
+            &self.official,
+            &self.aur,
+            &self.next,
+            &self.pending,
+        ]
+    }
+
+    fn append_to(&self, menu: &gtk::Menu) {
+        for item in self.all() {
+            menu.append(item);
+        }
+    }
+
+    fn set_packages(&self, snapshot: &UpdateSnapshot, actions: &PackageActions) {
+        for (item, updates) in [
+            (&self.official, &snapshot.official),
+            (&self.aur, &snapshot.aur),
+        ] {
+            if updates.is_empty() {
+                item.set_submenu(None::<&gtk::Menu>);
+                item.set_sensitive(false);
+            } else {
+                item.set_submenu(Some(&actions.package_list(updates)));
+                item.set_sensitive(true);
+            }
+        }
+    }
+
+    fn apply(&self, state: &AppState) {
+        let cached = if state.stale { " (cached)" } else { "" };
+        self.status
+            .set_label(&format!("Status: {}{cached}", status_text(state)));
+        self.official.set_label(&format!(
+            "Official updates: {} ({})",
+            state.official_count,
+            source_check_text(&state.official)
+        ));
+        let aur_check = state
+            .aur
+            .as_ref()
+            .map(source_check_text)
+            .unwrap_or_else(|| "disabled".to_string());
+        self.aur
+            .set_label(&format!("AUR updates: {} ({aur_check})", state.aur_count));
+
+        let next = state
+            .next_check
+            .map(|ts| ts.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "pending".to_string());
+        self.next.set_label(&format!("Next check: {next}"));
+
+        let pending = state
+            .oldest_pending
+            .as_ref()
+            .map(|(name, seen)| {
+                let days = (Local::now() - *seen).num_days();
+                format!("{name}, {}", pending_days_label(days))
+            })
+            .unwrap_or_else(|| "none".to_string());
+        self.pending
+            .set_label(&format!("Oldest pending: {pending}"));
+    }
+}
+
+/// Shows why the config file was rejected, or hides the line once it loads again.
+fn apply_config_error(item: &gtk::MenuItem, error: Option<&str>) {
+    match error {
+        Some(error) => {
+            item.set_label(&format!("⚠ Config error: {}", truncate_error(error)));
+            item.set_tooltip_text(Some(error));
+            item.set_visible(true);
+        }
+        None => item.set_visible(false),
+    }
+}
+
 fn apply_update_to_menu(
-    indicator: &AppIndicator,
-    status_item: &gtk::MenuItem,
-    official_item: &gtk::MenuItem,
-    aur_item: &gtk::MenuItem,
-    checked_item: &gtk::MenuItem,
+    indicator: &Indicator,
+    status_items: &StatusItems,
     state: &AppState,
     icon_dir: &Path,
+    config: &EffectiveConfig,
+    animation: &CheckingAnimation,
 ) {
-    status_item.set_label(&format!("Status: {}", status_text(state)));
-    official_item.set_label(&format!("Official updates: {}", state.official_count));
-    aur_item.set_label(&format!("AUR updates: {}", state.aur_count));
-
-    let checked = state
-        .last_checked
-        .map(|ts| ts.format("%Y-%m-%d %H:%M:%S").to_string())
-        .unwrap_or_else(|| "never".to_string());
-    checked_item.set_label(&format!("Last check: {checked}"));
+    status_items.apply(state);
 
     indicator.set_icon_theme_path(icon_dir);
-    let icon = choose_icon_name(&state.status);
-    indicator.set_icon(icon);
+    let custom = icons::custom_icon(
+        &config.icons,
+        &state.status,
+        prefers_dark_theme(),
+        icon_dir,
+        has_theme_icon,
+    );
+    let animate = state.status == Status::Checking && config.animate_checking && custom.is_none();
+    let icon = custom
+        .or_else(|| badge_icon(icon_dir, state, config))
+        .unwrap_or_else(|| choose_icon_name(&state.status).to_string());
+    let description = format!("Pactrack: {}", status_text(state));
+
+    if animate {
+        indicator.set_status(ItemStatus::Active);
+        animation.start(indicator, &description);
+    } else if matches!(state.status, Status::Overdue | Status::Error) {
+        animation.stop();
+        // Panels that support it highlight an indicator in the attention state.
+        indicator.set_attention_icon_full(&icon, &description);
+        indicator.set_status(ItemStatus::NeedsAttention);
+    } else {
+        animation.stop();
+        indicator.set_icon_full(&icon, &description);
+        indicator.set_status(ItemStatus::Active);
+    }
+
+    let pending = matches!(state.status, Status::UpdatesAvailable | Status::Overdue);
+    let label = if pending {
+        render_label(
+            &config.label_template,
+            state.official_count,
+            state.aur_count,
+        )
+    } else {
+        String::new()
+    };
+    // The guide reserves room for three-digit source counts (so a four-digit {total}) and
+    // keeps the panel from jumping around as counts change.
+    indicator.set_label(&label, &render_label(&config.label_template, 999, 999));
 }
 
-fn status_text(state: &AppState) -> String {
-    match state.status {
-        Status::Checking => "checking".to_string(),
-        Status::UpToDate => "up to date".to_string(),
-        Status::UpdatesAvailable => format!("{} updates available", state.total_count),
-        Status::Error => {
-            let msg = state
-                .last_error
-                .as_deref()
-                .map(truncate_error)
-                .unwrap_or_else(|| "unknown error".to_string());
-            format!("error ({msg})")
+/// When a source was last checked, e.g. "checked 2024-05-01 14:05", or a warning with the
+/// error when its latest check failed and the count is from an earlier check.
+fn source_check_text(source: &SourceState) -> String {
+    match source.status() {
+        SourceStatus::Checking => "checking".to_string(),
+        SourceStatus::NotChecked => "not checked yet".to_string(),
+        SourceStatus::Failed => {
+            let msg = source.last_error.as_deref().unwrap_or("unknown error");
+            format!("⚠ check failed: {}", truncate(msg, 48))
         }
+        SourceStatus::Ok(checked) => format!("checked {}", checked.format("%Y-%m-%d %H:%M")),
     }
 }
 
-fn truncate_error(msg: &str) -> String {
-    let max = 72usize;
-    if msg.chars().count() <= max {
-        msg.to_string()
-    } else {
-        msg.chars().take(max).collect::<String>() + "..."
+/// Delay between frames of the checking animation.
+const CHECKING_FRAME_INTERVAL: Duration = Duration::from_millis(150);
+
+/// Cycles the built-in checking frames while a check runs.
+#[derive(Default)]
+struct CheckingAnimation {
+    source: RefCell<Option<glib::SourceId>>,
+}
+
+impl CheckingAnimation {
+    /// Starts cycling unless already running.
+    fn start(&self, indicator: &Indicator, description: &str) {
+        if self.source.borrow().is_some() {
+            return;
+        }
+
+        indicator.set_icon_full(&icons::checking_frame_name(0), description);
+        let (indicator, description) = (indicator.clone(), description.to_string());
+        let mut frame = 0;
+        let source = glib::timeout_add_local(CHECKING_FRAME_INTERVAL, move || {
+            frame = (frame + 1) % icons::CHECKING_FRAMES;
+            indicator.set_icon_full(&icons::checking_frame_name(frame), &description);
+            ControlFlow::Continue
+        });
+        *self.source.borrow_mut() = Some(source);
+    }
+
+    fn stop(&self) {
+        if let Some(source) = self.source.borrow_mut().take() {
+            source.remove();
+        }
+    }
+}
+
+fn badge_icon(icon_dir: &Path, state: &AppState, config: &EffectiveConfig) -> Option<String> {
+    if !config.count_badge {
+        return None;
     }
+    icons::install_badge_icon(icon_dir, &state.status, state.total_count).unwrap_or_else(|err| {
+        warn!("failed to write badge icon: {err}");
+        None
+    })
 }
 
 fn choose_icon_name(status: &Status) -> &'static str {
     let (theme_icon, fallback_icon) = icons::icon_candidates(status);
-    if gtk::IconTheme::default()
-        .map(|theme| theme.has_icon(theme_icon))
-        .unwrap_or(false)
-    {
+    if has_theme_icon(theme_icon) {
         theme_icon
     } else {
         fallback_icon
     }
 }
 
+fn has_theme_icon(name: &str) -> bool {
+    gtk::IconTheme::default().is_some_and(|theme| theme.has_icon(name))
+}
+
+/// Whether the GTK theme is dark, through the dark-theme preference or a "-dark" theme
+/// such as Adwaita-dark.
+fn prefers_dark_theme() -> bool {
+    gtk::Settings::default().is_some_and(|settings| {
+        settings.is_gtk_application_prefer_dark_theme()
+            || settings
+                .gtk_theme_name()
+                .is_some_and(|name| name.to_lowercase().contains("dark"))
+    })
+}
+
+/// The tray icon, shown through the AppIndicator library or the built-in
+/// StatusNotifierItem.
+#[derive(Clone)]
+enum Indicator {
+    Library(AppIndicator),
+    Sni(Rc<SniIndicator>),
+}
+
+impl Indicator {
+    fn create(backend: TrayBackend) -> Result<Self, String> {
+        match backend {
+            TrayBackend::AppIndicator => Self::library(),
+            TrayBackend::Sni => Self::sni(),
+            TrayBackend::Auto => Self::library().or_else(|err| {
+                info!("{err}; using the built-in StatusNotifierItem");
+                Self::sni()
+            }),
+        }
+    }
+
+    fn library() -> Result<Self, String> {
+        let api = Arc::new(AppIndicatorApi::load()?);
+        AppIndicator::new(api, "pactrack", "software-update-available").map(Self::Library)
+    }
+
+    fn sni() -> Result<Self, String> {
+        let conn = zbus::blocking::Connection::session()
+            .map_err(|e| format!("cannot connect to the session bus: {e}"))?;
+        let props = ItemProps {
+            icon_name: "software-update-available".to_string(),
+            ..ItemProps::default()
+        };
+        let (item, events) = SniItem::start(conn, props.clone())
+            .map_err(|e| format!("failed to start StatusNotifierItem: {e}"))?;
+        let indicator = Rc::new(SniIndicator {
+            item,
+            props: RefCell::new(props),
+            menu: RefCell::default(),
+            items: RefCell::default(),
+            menus: RefCell::default(),
+            next_id: Cell::new(1),
+            export_queued: Cell::new(false),
+        });
+
+        let weak = Rc::downgrade(&indicator);
+        glib::MainContext::default().spawn_local(async move {
+            while let Ok(id) = events.recv().await {
+                let Some(indicator) = weak.upgrade() else {
+                    break;
+                };
+                indicator.activate(id);
+            }
+        });
+        Ok(Self::Sni(indicator))
+    }
+
+    fn set_title(&self, title: &str) {
+        match self {
+            Self::Library(indicator) => indicator.set_title(title),
+            Self::Sni(indicator) => indicator.update(|props| props.title = title.to_string()),
+        }
+    }
+
+    fn set_status(&self, status: ItemStatus) {
+        match self {
+            Self::Library(indicator) => indicator.set_status(status),
+            Self::Sni(indicator) => indicator.update(|props| props.status = status),
+        }
+    }
+
+    fn set_menu(&self, menu: &gtk::Menu) {
+        match self {
+            Self::Library(indicator) => indicator.set_menu(menu),
+            Self::Sni(indicator) => indicator.follow_menu(menu),
+        }
+    }
+
+    fn set_icon_full(&self, icon_name: &str, description: &str) {
+        match self {
+            Self::Library(indicator) => indicator.set_icon_full(icon_name, description),
+            Self::Sni(indicator) => indicator.update(|props| {
+                props.icon_name = icon_name.to_string();
+                props.description = description.to_string();
+            }),
+        }
+    }
+
+    fn set_attention_icon_full(&self, icon_name: &str, description: &str) {
+        match self {
+            Self::Library(indicator) => indicator.set_attention_icon_full(icon_name, description),
+            Self::Sni(indicator) => indicator.update(|props| {
+                props.attention_icon_name = icon_name.to_string();
+                props.description = description.to_string();
+            }),
+        }
+    }
+
+    fn set_label(&self, label: &str, guide: &str) {
+        match self {
+            Self::Library(indicator) => indicator.set_label(label, guide),
+            Self::Sni(indicator) => indicator.update(|props| {
+                props.label = label.to_string();
+                props.label_guide = guide.to_string();
+            }),
+        }
+    }
+
+    fn set_icon_theme_path(&self, path: &Path) {
+        match self {
+            Self::Library(indicator) => indicator.set_icon_theme_path(path),
+            Self::Sni(indicator) => {
+                indicator.update(|props| props.icon_theme_path = path.display().to_string())
+            }
+        }
+    }
+}
+
+struct SniIndicator {
+    item: SniItem,
+    props: RefCell<ItemProps>,
+    /// The GTK menu mirrored over dbusmenu.
+    menu: RefCell<Option<gtk::Menu>>,
+    /// Mirrored items with their dbusmenu id, which they keep while they are in the menu.
+    items: RefCell<HashMap<gtk::MenuItem, MirroredItem>>,
+    /// Mirrored (sub)menus with their insert/remove handlers.
+    menus: RefCell<HashMap<gtk::Menu, Vec<glib::SignalHandlerId>>>,
+    next_id: Cell<i32>,
+    /// Set while an export is queued, so a burst of changes is exported once.
+    export_queued: Cell<bool>,
+}
+
+struct MirroredItem {
+    id: i32,
+    handlers: Vec<glib::SignalHandlerId>,
+}
+
+impl SniIndicator {
+    fn update(&self, change: impl FnOnce(&mut ItemProps)) {
+        let mut props = self.props.borrow_mut();
+        change(&mut props);
+        if let Err(err) = self.item.set_props(props.clone()) {
+            warn!("failed to update StatusNotifierItem: {err}");
+        }
+    }
+
+    /// Exports `menu` over dbusmenu, and again whenever an item's label, visibility,
+    /// sensitivity or submenu changes or items are inserted or removed.
+    fn follow_menu(self: &Rc<Self>, menu: &gtk::Menu) {
+        *self.menu.borrow_mut() = Some(menu.clone());
+        self.export();
+    }
+
+    /// Activates the item the host reported a click on. Ids of items that have left the
+    /// menu since the host fetched it are ignored.
+    fn activate(&self, id: i32) {
+        let item = self
+            .items
+            .borrow()
+            .iter()
+            .find(|(_, mirrored)| mirrored.id == id)
+            .map(|(item, _)| item.clone());
+        match item {
+            Some(item) => {
+                item.activate();
+            }
+            None => debug!("ignoring click on stale tray menu id {id}"),
+        }
+    }
+
+    fn queue_export(self: &Rc<Self>) {
+        if self.export_queued.replace(true) {
+            return;
+        }
+        let indicator = Rc::clone(self);
+        glib::idle_add_local_once(move || {
+            indicator.export_queued.set(false);
+            indicator.export();
+        });
+    }
+
+    fn export(self: &Rc<Self>) {
+        let Some(menu) = self.menu.borrow().clone() else {
+            return;
+        };
+        let mut seen_items = HashSet::new();
+        let mut seen_menus = HashSet::new();
+        let nodes = self.mirror(&menu, &mut seen_items, &mut seen_menus);
+
+        // Widgets that left the menu lose their id and stop triggering exports.
+        self.items.borrow_mut().retain(|item, mirrored| {
+            let keep = seen_items.contains(item);
+            if !keep {
+                for handler in mirrored.handlers.drain(..) {
+                    item.disconnect(handler);
+                }
+            }
+            keep
+        });
+        self.menus.borrow_mut().retain(|menu, handlers| {
+            let keep = seen_menus.contains(menu);
+            if !keep {
+                for handler in handlers.drain(..) {
+                    menu.disconnect(handler);
+                }
+            }
+            keep
+        });
+
+        if let Err(err) = self.item.set_menu(nodes) {
+            warn!("failed to export tray menu: {err}");
+        }
+    }
+
+    /// Converts `menu` into dbusmenu nodes, connecting change signals on widgets seen for
+    /// the first time.
+    fn mirror(
+        self: &Rc<Self>,
+        menu: &gtk::Menu,
+        seen_items: &mut HashSet<gtk::MenuItem>,
+        seen_menus: &mut HashSet<gtk::Menu>,
+    ) -> Vec<MenuNode> {
+        seen_menus.insert(menu.clone());
+        if !self.menus.borrow().contains_key(menu) {
+            let (on_insert, on_remove) = (Rc::downgrade(self), Rc::downgrade(self));
+            let handlers = vec![
+                menu.connect_insert(move |_, _, _| queue_export(&on_insert)),
+                menu.connect_remove(move |_, _| queue_export(&on_remove)),
+            ];
+            self.menus.borrow_mut().insert(menu.clone(), handlers);
+        }
+
+        menu.children()
+            .into_iter()
+            .filter_map(|widget| widget.downcast::<gtk::MenuItem>().ok())
+            .map(|item| {
+                seen_items.insert(item.clone());
+                let id = self.item_id(&item);
+                let children = item
+                    .submenu()
+                    .and_then(|submenu| submenu.downcast::<gtk::Menu>().ok())
+                    .map(|submenu| self.mirror(&submenu, seen_items, seen_menus))
+                    .unwrap_or_default();
+                MenuNode {
+                    id,
+                    label: item
+                        .label()
+                        .map(|label| label.to_string())
+                        .unwrap_or_default(),
+                    enabled: item.is_sensitive(),
+                    visible: item.is_visible(),
+                    separator: item.is::<gtk::SeparatorMenuItem>(),
+                    children,
+                }
+            })
+            .collect()
+    }
+
+    /// The dbusmenu id of `item`, assigned (and its change signals connected) on first sight.
+    fn item_id(self: &Rc<Self>, item: &gtk::MenuItem) -> i32 {
+        if let Some(mirrored) = self.items.borrow().get(item) {
+            return mirrored.id;
+        }
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let handlers = ["label", "visible", "sensitive", "submenu"]
+            .into_iter()
+            .map(|property| {
+                let indicator = Rc::downgrade(self);
+                item.connect_notify_local(Some(property), move |_, _| queue_export(&indicator))
+            })
+            .collect();
+        self.items
+            .borrow_mut()
+            .insert(item.clone(), MirroredItem { id, handlers });
+        id
+    }
+}
+
+/// Signal handlers hold the indicator weakly, so the widgets do not keep it alive.
+fn queue_export(indicator: &Weak<SniIndicator>) {
+    if let Some(indicator) = indicator.upgrade() {
+        indicator.queue_export();
+    }
+}
+
 #[derive(Clone)]
 struct AppIndicator {
     api: Arc<AppIndicatorApi>,
@@ -314,8 +972,12 @@ impl AppIndicator {
         Ok(Self { api, raw })
     }
 
-    fn set_status_active(&self) {
-        unsafe { (self.api.set_status)(self.raw, APP_INDICATOR_STATUS_ACTIVE) };
+    fn set_status(&self, status: ItemStatus) {
+        let status = match status {
+            ItemStatus::Active => APP_INDICATOR_STATUS_ACTIVE,
+            ItemStatus::NeedsAttention => APP_INDICATOR_STATUS_ATTENTION,
+        };
+        unsafe { (self.api.set_status)(self.raw, status) };
     }
 
     fn set_menu(&self, menu: &gtk::Menu) {
@@ -324,20 +986,46 @@ impl AppIndicator {
         }
     }
 
-    fn set_icon(&self, icon_name: &str) {
-        if let Ok(icon) = CString::new(icon_name) {
+    /// `description` is the accessible description read out by screen readers.
+    fn set_icon_full(&self, icon_name: &str, description: &str) {
+        if let (Ok(icon), Ok(description)) = (CString::new(icon_name), CString::new(description)) {
+            unsafe {
+                (self.api.set_icon_full)(self.raw, icon.as_ptr(), description.as_ptr());
+            }
+        }
+    }
+
+    fn set_attention_icon_full(&self, icon_name: &str, description: &str) {
+        if let (Ok(icon), Ok(description)) = (CString::new(icon_name), CString::new(description)) {
+            unsafe {
+                (self.api.set_attention_icon_full)(self.raw, icon.as_ptr(), description.as_ptr());
+            }
+        }
+    }
+
+    /// `guide` is the longest expected label; panels use it to size the label area.
+    fn set_label(&self, label: &str, guide: &str) {
+        if let (Ok(label), Ok(guide)) = (CString::new(label), CString::new(guide)) {
+            unsafe {
+                (self.api.set_label)(self.raw, label.as_ptr(), guide.as_ptr());
+            }
+        }
+    }
+
+    fn set_title(&self, title: &str) {
+        if let Ok(title) = CString::new(title) {
             unsafe {
-                (self.api.set_icon)(self.raw, icon.as_ptr());
+                (self.api.set_title)(self.raw, title.as_ptr());
             }
         }
     }
 
     fn set_icon_theme_path(&self, path: &Path) {
-        if let Some(path) = path.to_str() {
-            if let Ok(path) = CString::new(path) {
-                unsafe {
-                    (self.api.set_icon_theme_path)(self.raw, path.as_ptr());
-                }
+        if let Some(path) = path.to_str()
+            && let Ok(path) = CString::new(path)
+        {
+            unsafe {
+                (self.api.set_icon_theme_path)(self.raw, path.as_ptr());
             }
         }
     }
@@ -348,8 +1036,11 @@ struct AppIndicatorApi {
     new: unsafe extern "C" fn(*const c_char, *const c_char, c_int) -> *mut c_void,
     set_status: unsafe extern "C" fn(*mut c_void, c_int),
     set_menu: unsafe extern "C" fn(*mut c_void, *mut c_void),
-    set_icon: unsafe extern "C" fn(*mut c_void, *const c_char),
+    set_icon_full: unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char),
+    set_attention_icon_full: unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char),
     set_icon_theme_path: unsafe extern "C" fn(*mut c_void, *const c_char),
+    set_label: unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char),
+    set_title: unsafe extern "C" fn(*mut c_void, *const c_char),
 }
 
 impl AppIndicatorApi {
@@ -381,9 +1072,14 @@ impl AppIndicatorApi {
         let set_menu = unsafe {
             *lib.get::<unsafe extern "C" fn(*mut c_void, *mut c_void)>(b"app_indicator_set_menu\0")?
         };
-        let set_icon = unsafe {
-            *lib.get::<unsafe extern "C" fn(*mut c_void, *const c_char)>(
-                b"app_indicator_set_icon\0",
+        let set_icon_full = unsafe {
+            *lib.get::<unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char)>(
+                b"app_indicator_set_icon_full\0",
+            )?
+        };
+        let set_attention_icon_full = unsafe {
+            *lib.get::<unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char)>(
+                b"app_indicator_set_attention_icon_full\0",
             )?
         };
         let set_icon_theme_path = unsafe {
@@ -391,17 +1087,31 @@ impl AppIndicatorApi {
                 b"app_indicator_set_icon_theme_path\0",
             )?
         };
+        let set_label = unsafe {
+            *lib.get::<unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char)>(
+                b"app_indicator_set_label\0",
+            )?
+        };
+        let set_title = unsafe {
+            *lib.get::<unsafe extern "C" fn(*mut c_void, *const c_char)>(
+                b"app_indicator_set_title\0",
+            )?
+        };
 
         Ok(Self {
             _lib: lib,
             new,
             set_status,
             set_menu,
-            set_icon,
+            set_icon_full,
+            set_attention_icon_full,
             set_icon_theme_path,
+            set_label,
+            set_title,
         })
     }
 }
 
 const APP_INDICATOR_CATEGORY_APPLICATION_STATUS: c_int = 0;
 const APP_INDICATOR_STATUS_ACTIVE: c_int = 1;
+const APP_INDICATOR_STATUS_ATTENTION: c_int = 2;
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+
+use crate::commands::DetectedAurHelper;
+use crate::report::{plural, summarize_names};
+use crate::state::{PackageUpdate, UpdateSnapshot, UpdateSource};
+
+const WARNING_NAME_LIMIT: usize = 5;
+
+/// Updates ticked for an upgrade. Keyed by source as well, since the same name can be
+/// pending from the repos and the AUR.
+pub type Selection = HashSet<(UpdateSource, String)>;
+
+pub fn selection_key(update: &PackageUpdate) -> (UpdateSource, String) {
+    (update.source, update.name.clone())
+}
+
+/// What to run for a chosen subset of pending updates, and what to tell the user first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UpgradePlan {
+    /// `None` when nothing can be upgraded (nothing selected, or only AUR packages without
+    /// a helper).
+    pub command: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Plans an upgrade of the updates in `snapshot` that are in `selected`.
+///
+/// Official packages are never installed one by one against a stale sync DB. Instead the
+/// whole system is upgraded with the unselected and snoozed packages held back through
+/// `--ignore`, so the DB is refreshed and pacman still resolves dependencies (and asks before
+/// pulling in a held-back package). Holding anything back is a partial upgrade, and the plan
+/// says so. A custom `upgrade_cmd` replaces `sudo pacman -Syu`; since it may be an AUR helper
+/// upgrading everything, held-back AUR packages are passed to its `--ignore` as well.
+pub fn plan_upgrade(
+    snapshot: &UpdateSnapshot,
+    selected: &Selection,
+    helper: Option<DetectedAurHelper>,
+    upgrade_cmd: &str,
+) -> UpgradePlan {
+    let is_selected = |update: &&PackageUpdate| selected.contains(&selection_key(update));
+    let snoozed = |source: UpdateSource| {
+        snapshot
+            .snoozed
+            .iter()
+            .filter(move |update| update.source == source)
+    };
+    let (official, mut held_official): (Vec<&PackageUpdate>, Vec<&PackageUpdate>) =
+        snapshot.official.iter().partition(is_selected);
+    held_official.extend(snoozed(UpdateSource::Official));
+    let (aur, mut held_aur): (Vec<&PackageUpdate>, Vec<&PackageUpdate>) =
+        snapshot.aur.iter().partition(is_selected);
+    held_aur.extend(snoozed(UpdateSource::Aur));
+
+    let custom = upgrade_cmd != "auto";
+    let mut steps = Vec::new();
+    let mut warnings = Vec::new();
+
+    if !official.is_empty() {
+        let mut ignored: Vec<&str> = held_official.iter().map(|u| u.name.as_str()).collect();
+        if custom {
+            ignored.extend(held_aur.iter().map(|u| u.name.as_str()));
+        }
+        let base = if custom {
+            upgrade_cmd
+        } else {
+            "sudo pacman -Syu"
+        };
+        if ignored.is_empty() {
+            steps.push(base.to_string());
+        } else {
+            steps.push(format!(
+                "{base} --ignore {}",
+                shell_words::quote(&ignored.join(","))
+            ));
+        }
+
+        if !held_official.is_empty() {
+            let held: Vec<&str> = held_official.iter().map(|u| u.name.as_str()).collect();
+            warnings.push(format!(
+                "Partial upgrade: holding back {} official {} ({}). Arch does not support \
+                 partial upgrades; pacman may still need some of them, so upgrade them soon.",
+                held.len(),
+                plural(held.len(), "update", "updates"),
+                summarize_names(&held, WARNING_NAME_LIMIT)
+            ));
+        }
+    }
+
+    if !aur.is_empty() {
+        match helper {
+            Some(helper) => {
+                let names: Vec<String> = aur
+                    .iter()
+                    .map(|u| shell_words::quote(&u.name).to_string())
+                    .collect();
+                steps.push(format!("{} -S {}", helper.binary(), names.join(" ")));
+            }
+            None => warnings
+                .push("No AUR helper found; the selected AUR packages are skipped.".to_string()),
+        }
+
+        if helper.is_some() && !held_official.is_empty() {
+            warnings.push(format!(
+                "The AUR packages will be built against {} official {} that {} not upgraded.",
+                held_official.len(),
+                plural(held_official.len(), "package", "packages"),
+                plural(held_official.len(), "is", "are")
+            ));
+        }
+    }
+
+    for hint in &snapshot.rebuilds {
+        if !selected.contains(&(UpdateSource::Official, hint.package.clone()))
+            || hint.aur_dependents.is_empty()
+        {
+            continue;
+        }
+        let unselected: Vec<&str> = hint
+            .aur_dependents
+            .iter()
+            .filter(|name| !selected.contains(&(UpdateSource::Aur, (*name).clone())))
+            .map(String::as_str)
+            .collect();
+        if !unselected.is_empty() {
+            warnings.push(format!(
+                "{} changes; AUR packages built against it may need a rebuild: {}.",
+                hint.package,
+                summarize_names(&unselected, WARNING_NAME_LIMIT)
+            ));
+        }
+    }
+
+    UpgradePlan {
+        command: (!steps.is_empty()).then(|| steps.join(" && ")),
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::RebuildHint;
+
+    fn snapshot() -> UpdateSnapshot {
+        UpdateSnapshot {
+            official: ["firefox", "icu", "linux"]
+                .iter()
+                .map(|name| PackageUpdate::new(name, "1-1", "2-1", UpdateSource::Official))
+                .collect(),
+            aur: vec![PackageUpdate::new(
+                "foo-git",
+                "r1-1",
+                "r2-1",
+                UpdateSource::Aur,
+            )],
+            rebuilds: vec![RebuildHint {
+                package: "icu".to_string(),
+                current: "74.1-1".to_string(),
+                latest: "75.1-1".to_string(),
+                dependents: 2,
+                aur_dependents: vec!["foo-git".to_string()],
+                minor_bump: false,
+            }],
+            snoozed: Vec::new(),
+        }
+    }
+
+    /// Ticks `names`; `foo-git` is the only AUR update in the fixture.
+    fn names(names: &[&str]) -> Selection {
+        names
+            .iter()
+            .map(|name| {
+                let source = if name.ends_with("-git") {
+                    UpdateSource::Aur
+                } else {
+                    UpdateSource::Official
+                };
+                (source, name.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn selecting_everything_is_a_full_upgrade() {
+        let plan = plan_upgrade(
+            &snapshot(),
+            &names(&["firefox", "icu", "linux", "foo-git"]),
+            Some(DetectedAurHelper::Paru),
+            "auto",
+        );
+
+        assert_eq!(
+            plan.command.as_deref(),
+            Some("sudo pacman -Syu && paru -S foo-git")
+        );
+        assert!(plan.warnings.is_empty());
+    }
+
+    #[test]
+    fn unselected_official_updates_are_ignored_and_warned_about() {
+        let plan = plan_upgrade(
+            &snapshot(),
+            &names(&["firefox", "icu"]),
+            Some(DetectedAurHelper::Paru),
+            "auto",
+        );
+
+        assert_eq!(
+            plan.command.as_deref(),
+            Some("sudo pacman -Syu --ignore linux")
+        );
+        assert_eq!(plan.warnings.len(), 2);
+        assert!(plan.warnings[0].starts_with("Partial upgrade: holding back 1 official update"));
+        assert!(plan.warnings[1].contains("may need a rebuild: foo-git"));
+    }
+
+    #[test]
+    fn aur_only_selection_warns_about_outdated_system() {
+        let plan = plan_upgrade(
+            &snapshot(),
+            &names(&["foo-git"]),
+            Some(DetectedAurHelper::Yay),
+            "auto",
+        );
+        assert_eq!(plan.command.as_deref(), Some("yay -S foo-git"));
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.warnings[0].contains("3 official packages that are not upgraded"));
+
+        let plan = plan_upgrade(&snapshot(), &names(&["foo-git"]), None, "auto");
+        assert_eq!(plan.command, None);
+    }
+
+    #[test]
+    fn empty_selection_has_no_command() {
+        let plan = plan_upgrade(&snapshot(), &Selection::new(), None, "auto");
+        assert_eq!(plan.command, None);
+        assert!(plan.warnings.is_empty());
+    }
+
+    #[test]
+    fn snoozed_updates_are_held_back_even_when_everything_is_ticked() {
+        let mut snapshot = snapshot();
+        snapshot.snoozed.push(PackageUpdate::new(
+            "mesa",
+            "1-1",
+            "2-1",
+            UpdateSource::Official,
+        ));
+        let plan = plan_upgrade(
+            &snapshot,
+            &names(&["firefox", "icu", "linux", "foo-git"]),
+            Some(DetectedAurHelper::Paru),
+            "auto",
+        );
+
+        assert_eq!(
+            plan.command.as_deref(),
+            Some("sudo pacman -Syu --ignore mesa && paru -S foo-git")
+        );
+        assert!(plan.warnings[0].contains("holding back 1 official update (mesa)"));
+    }
+
+    #[test]
+    fn custom_upgrade_command_gets_every_held_package() {
+        let plan = plan_upgrade(
+            &snapshot(),
+            &names(&["firefox", "icu"]),
+            None,
+            "paru -Syu --noconfirm",
+        );
+        assert_eq!(
+            plan.command.as_deref(),
+            Some("paru -Syu --noconfirm --ignore linux,foo-git")
+        );
+
+        let everything = names(&["firefox", "icu", "linux"]);
+        let plan = plan_upgrade(&snapshot(), &everything, None, "sudo pacman -Syu");
+        assert_eq!(
+            plan.command.as_deref(),
+            Some("sudo pacman -Syu --ignore foo-git")
+        );
+    }
+
+    #[test]
+    fn selection_matches_name_and_source() {
+        let mut snapshot = snapshot();
+        snapshot
+            .aur
+            .push(PackageUpdate::new("linux", "6-1", "7-1", UpdateSource::Aur));
+        let mut selected = names(&["firefox", "icu"]);
+        selected.insert((UpdateSource::Aur, "linux".to_string()));
+
+        let plan = plan_upgrade(&snapshot, &selected, Some(DetectedAurHelper::Paru), "auto");
+        assert_eq!(
+            plan.command.as_deref(),
+            Some("sudo pacman -Syu --ignore linux && paru -S linux")
+        );
+    }
+}
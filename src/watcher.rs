@@ -0,0 +1,115 @@
+use std::ffi::OsString;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use log::{debug, warn};
+
+/// A directory to watch with inotify, optionally narrowed to entries with the given names.
+pub struct WatchSpec {
+    pub dir: PathBuf,
+    pub mask: WatchMask,
+    /// Empty means any entry in `dir` counts.
+    pub names: Vec<OsString>,
+}
+
+impl WatchSpec {
+    fn matches(&self, name: Option<&OsString>) -> bool {
+        self.names.is_empty() || name.is_some_and(|name| self.names.contains(name))
+    }
+}
+
+/// Watches `specs` on background threads and calls `on_change` once a burst of matching
+/// events has been quiet for `debounce`. The watcher stops when `on_change` returns false.
+pub fn spawn_debounced_watcher(
+    specs: Vec<WatchSpec>,
+    debounce: Duration,
+    mut on_change: impl FnMut() -> bool + Send + 'static,
+) -> io::Result<()> {
+    let mut inotify = Inotify::init()?;
+    let mut watches: Vec<(WatchDescriptor, WatchSpec)> = Vec::new();
+    for spec in specs {
+        let wd = inotify.watches().add(&spec.dir, spec.mask)?;
+        watches.push((wd, spec));
+    }
+
+    let (event_tx, event_rx) = mpsc::channel::<()>();
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(err) => {
+                    warn!("inotify read failed, stopping watcher: {err}");
+                    return;
+                }
+            };
+
+            for event in events {
+                let name = event.name.map(OsString::from);
+                let relevant = watches
+                    .iter()
+                    .any(|(wd, spec)| *wd == event.wd && spec.matches(name.as_ref()));
+                if relevant && event_tx.send(()).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        while event_rx.recv().is_ok() {
+            loop {
+                match event_rx.recv_timeout(debounce) {
+                    Ok(()) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            if !on_change() {
+                debug!("watcher callback asked to stop");
+                return;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn debounces_bursts_and_filters_names() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let (tx, rx) = mpsc::channel();
+        spawn_debounced_watcher(
+            vec![WatchSpec {
+                dir: temp.path().to_path_buf(),
+                mask: WatchMask::CREATE | WatchMask::DELETE,
+                names: vec![OsString::from("db.lck")],
+            }],
+            Duration::from_millis(100),
+            move || tx.send(()).is_ok(),
+        )
+        .expect("start watcher");
+
+        fs::write(temp.path().join("unrelated"), "").expect("write unrelated");
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+        let lock = temp.path().join("db.lck");
+        fs::write(&lock, "").expect("create lock");
+        fs::remove_file(&lock).expect("remove lock");
+
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+}